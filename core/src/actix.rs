@@ -0,0 +1,286 @@
+/*!
+ * [actix-web](https://crates.io/crates/actix-web) integration.
+ *
+ * ```no_run
+ * async fn count(
+ *     connection: elephantry::actix::Connection,
+ * ) -> elephantry::Result<String> {
+ *     let count = connection
+ *         .r#async()
+ *         .query_one::<i64>("select count(*) from event", &[])
+ *         .await?;
+ *
+ *     Ok(count.to_string())
+ * }
+ *
+ * async fn delete(transaction: elephantry::actix::Transaction) -> elephantry::Result<String> {
+ *     transaction.r#async().execute("delete from event").await?;
+ *     transaction.r#async().execute("delete from log").await?;
+ *
+ *     Ok(String::new())
+ * }
+ *
+ * let pool = elephantry::AsyncPool::new("postgres://localhost");
+ *
+ * let app = actix_web::App::new()
+ *     .app_data(actix_web::web::Data::new(pool))
+ *     .wrap(actix_web::middleware::from_fn(elephantry::actix::transaction))
+ *     .route("/", actix_web::web::get().to(count))
+ *     .route("/", actix_web::web::delete().to(delete));
+ * ```
+ */
+
+type Slot = std::rc::Rc<std::cell::RefCell<Option<crate::AsyncPooled>>>;
+
+/**
+ * Extracts a connection from the [`AsyncPool`] registered as
+ * `web::Data<AsyncPool>` application data.
+ *
+ * [`AsyncPool`]: crate::AsyncPool
+ */
+#[derive(Debug)]
+pub struct Connection(pub crate::AsyncPooled);
+
+impl Connection {
+    async fn get(pool: Option<actix_web::web::Data<crate::AsyncPool>>) -> actix_web::Result<Self> {
+        let Some(pool) = pool else {
+            log::error!("The Connection extractor requires a web::Data<AsyncPool>");
+
+            return Err(actix_web::error::ErrorInternalServerError(
+                "Missing connection pool",
+            ));
+        };
+
+        Ok(Self(pool.get().await?))
+    }
+}
+
+impl actix_web::FromRequest for Connection {
+    type Error = actix_web::Error;
+    type Future = std::pin::Pin<Box<dyn std::future::Future<Output = actix_web::Result<Self>>>>;
+
+    fn from_request(
+        request: &actix_web::HttpRequest,
+        _: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let pool = request
+            .app_data::<actix_web::web::Data<crate::AsyncPool>>()
+            .cloned();
+
+        Box::pin(Self::get(pool))
+    }
+}
+
+impl std::ops::Deref for Connection {
+    type Target = crate::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/**
+ * Extracts a connection in a transaction, committed by the [`transaction`]
+ * middleware if the response is successful and rolled back otherwise.
+ *
+ * [`transaction`]: fn.transaction.html
+ */
+#[derive(Debug)]
+pub struct Transaction {
+    connection: Option<crate::AsyncPooled>,
+    slot: Slot,
+}
+
+impl Transaction {
+    async fn begin(
+        pool: Option<actix_web::web::Data<crate::AsyncPool>>,
+        slot: Option<Slot>,
+    ) -> actix_web::Result<Self> {
+        let Some(slot) = slot else {
+            log::error!("The Transaction extractor requires the transaction middleware");
+
+            return Err(actix_web::error::ErrorInternalServerError(
+                "Missing transaction middleware",
+            ));
+        };
+
+        let Connection(connection) = Connection::get(pool).await?;
+        connection.r#async().execute("begin transaction").await?;
+
+        Ok(Self {
+            connection: Some(connection),
+            slot,
+        })
+    }
+}
+
+impl actix_web::FromRequest for Transaction {
+    type Error = actix_web::Error;
+    type Future = std::pin::Pin<Box<dyn std::future::Future<Output = actix_web::Result<Self>>>>;
+
+    fn from_request(
+        request: &actix_web::HttpRequest,
+        _: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        use actix_web::HttpMessage as _;
+
+        let pool = request
+            .app_data::<actix_web::web::Data<crate::AsyncPool>>()
+            .cloned();
+        let slot = request.extensions().get::<Slot>().cloned();
+
+        Box::pin(Self::begin(pool, slot))
+    }
+}
+
+impl std::ops::Deref for Transaction {
+    type Target = crate::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        // Gives the connection back to the middleware.
+        *self.slot.borrow_mut() = self.connection.take();
+    }
+}
+
+/**
+ * Middleware ending the transactions opened by the [`Transaction`]
+ * extractor, to use with [`actix_web::middleware::from_fn`].
+ */
+pub async fn transaction<B: actix_web::body::MessageBody>(
+    request: actix_web::dev::ServiceRequest,
+    next: actix_web::middleware::Next<B>,
+) -> actix_web::Result<actix_web::dev::ServiceResponse<B>> {
+    use actix_web::HttpMessage as _;
+
+    let slot = Slot::default();
+    request.extensions_mut().insert(slot.clone());
+
+    let response = next.call(request).await?;
+
+    let Some(connection) = slot.borrow_mut().take() else {
+        return Ok(response);
+    };
+
+    let query = if response.status().is_client_error() || response.status().is_server_error() {
+        crate::transaction::rollback_query(None)
+    } else {
+        "commit transaction".to_string()
+    };
+
+    connection.r#async().execute(&query).await?;
+
+    Ok(response)
+}
+
+impl actix_web::ResponseError for crate::Error {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        actix_web::http::StatusCode::from_u16(crate::Error::status_code(self))
+            .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        let status = actix_web::ResponseError::status_code(self);
+
+        if status.is_server_error() {
+            log::error!("{self}");
+        } else {
+            log::debug!("{self}");
+        }
+
+        actix_web::HttpResponse::new(status)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    async fn insert(
+        transaction: crate::actix::Transaction,
+        id: actix_web::web::Path<i32>,
+    ) -> crate::Result<String> {
+        transaction
+            .r#async()
+            .query_one::<i32>(
+                "insert into actix_test values ($1) returning id",
+                &[&id.into_inner()],
+            )
+            .await?;
+
+        Ok(String::new())
+    }
+
+    async fn fail(transaction: crate::actix::Transaction) -> crate::Result<String> {
+        transaction
+            .r#async()
+            .execute("insert into actix_test values (100)")
+            .await?;
+
+        Err(crate::Error::NotFound)
+    }
+
+    async fn count(connection: crate::actix::Connection) -> crate::Result<String> {
+        let count = connection
+            .r#async()
+            .query_one::<i64>("select count(*) from actix_test", &[])
+            .await?;
+
+        Ok(count.to_string())
+    }
+
+    #[test]
+    fn actix() -> crate::Result {
+        use actix_web::test::{TestRequest, call_service, init_service};
+
+        actix_web::rt::System::new().block_on(async {
+            // A single connection keeps the temporary table.
+            let pool = crate::AsyncPool::with_options(
+                &crate::test::dsn(),
+                crate::PoolOptions {
+                    max_size: 1,
+                    ..Default::default()
+                },
+            );
+            pool.get()
+                .await?
+                .r#async()
+                .execute("create temporary table actix_test (id int primary key)")
+                .await?;
+
+            let app = init_service(
+                actix_web::App::new()
+                    .app_data(actix_web::web::Data::new(pool.clone()))
+                    .wrap(actix_web::middleware::from_fn(crate::actix::transaction))
+                    .route("/", actix_web::web::get().to(count))
+                    .route("/", actix_web::web::delete().to(fail))
+                    .route("/{id}", actix_web::web::post().to(insert)),
+            )
+            .await;
+
+            let status = |request: TestRequest| {
+                let app = &app;
+
+                async move { call_service(app, request.to_request()).await.status() }
+            };
+
+            assert_eq!(status(TestRequest::post().uri("/1")).await, 200);
+            assert_eq!(status(TestRequest::post().uri("/1")).await, 409);
+            assert_eq!(status(TestRequest::delete().uri("/")).await, 404);
+            assert_eq!(status(TestRequest::get().uri("/")).await, 200);
+
+            let count = pool
+                .get()
+                .await?
+                .r#async()
+                .query_one::<i64>("select count(*) from actix_test", &[])
+                .await?;
+            assert_eq!(count, 1);
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,268 @@
+/**
+ * Advisory lock identifier.
+ *
+ * PostgreSQL accepts either a single 64-bit key or a pair of 32-bit keys, both
+ * key spaces do not overlap.
+ *
+ * See <https://www.postgresql.org/docs/current/explicit-locking.html#ADVISORY-LOCKS>
+ */
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Key {
+    Single(i64),
+    Pair(i32, i32),
+}
+
+impl Key {
+    /**
+     * Creates a key from a string, using the FNV-1a hash function.
+     *
+     * The result is stable across processes and platforms, so every
+     * application computing the key for the same name lock the same object.
+     */
+    #[must_use]
+    pub fn hash(name: &str) -> Self {
+        let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+
+        Self::Single(hash as i64)
+    }
+
+    fn placeholders(&self) -> &'static str {
+        match self {
+            Self::Single(_) => "$1",
+            Self::Pair(_, _) => "$1, $2",
+        }
+    }
+
+    fn params(&self) -> Vec<&dyn crate::ToSql> {
+        match self {
+            Self::Single(key) => vec![key],
+            Self::Pair(key1, key2) => vec![key1, key2],
+        }
+    }
+}
+
+impl From<i64> for Key {
+    fn from(key: i64) -> Self {
+        Self::Single(key)
+    }
+}
+
+impl From<(i32, i32)> for Key {
+    fn from((key1, key2): (i32, i32)) -> Self {
+        Self::Pair(key1, key2)
+    }
+}
+
+impl From<&str> for Key {
+    fn from(name: &str) -> Self {
+        Self::hash(name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Mode {
+    /**
+     * Only one session can hold the lock.
+     */
+    #[default]
+    Exclusive,
+    /**
+     * The lock can be held by several sessions, but conflicts with exclusive
+     * lock.
+     */
+    Shared,
+}
+
+impl Mode {
+    fn suffix(&self) -> &'static str {
+        match self {
+            Self::Exclusive => "",
+            Self::Shared => "_shared",
+        }
+    }
+}
+
+/**
+ * A session-level advisory lock, released when dropped.
+ *
+ * This `struct` is created by the [`Connection::advisory_lock`] and
+ * [`Connection::try_advisory_lock`] methods.
+ *
+ * [`Connection::advisory_lock`]: crate::Connection::advisory_lock
+ * [`Connection::try_advisory_lock`]: crate::Connection::try_advisory_lock
+ */
+#[derive(Debug)]
+pub struct AdvisoryLock<'c> {
+    connection: &'c crate::Connection,
+    key: Key,
+    mode: Mode,
+    locked: bool,
+}
+
+impl<'c> AdvisoryLock<'c> {
+    pub(crate) fn lock(
+        connection: &'c crate::Connection,
+        key: Key,
+        mode: Mode,
+    ) -> crate::Result<Self> {
+        let query = query("advisory_lock", key, mode);
+        connection.query_one::<()>(&query, &key.params())?;
+
+        Ok(Self::new(connection, key, mode))
+    }
+
+    pub(crate) fn try_lock(
+        connection: &'c crate::Connection,
+        key: Key,
+        mode: Mode,
+    ) -> crate::Result<Option<Self>> {
+        let query = query("try_advisory_lock", key, mode);
+
+        if connection.query_one::<bool>(&query, &key.params())? {
+            Ok(Some(Self::new(connection, key, mode)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn new(connection: &'c crate::Connection, key: Key, mode: Mode) -> Self {
+        Self {
+            connection,
+            key,
+            mode,
+            locked: true,
+        }
+    }
+
+    #[must_use]
+    pub fn key(&self) -> Key {
+        self.key
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /**
+     * Explicitly release the lock. Unlike dropping the guard, this method
+     * reports errors.
+     */
+    pub fn unlock(mut self) -> crate::Result {
+        self.release()
+    }
+
+    fn release(&mut self) -> crate::Result {
+        if !self.locked {
+            return Ok(());
+        }
+
+        self.locked = false;
+
+        let query = query("advisory_unlock", self.key, self.mode);
+
+        if self
+            .connection
+            .query_one::<bool>(&query, &self.key.params())?
+        {
+            Ok(())
+        } else {
+            Err(crate::Error::AdvisoryLock(self.key))
+        }
+    }
+}
+
+impl Drop for AdvisoryLock<'_> {
+    fn drop(&mut self) {
+        if let Err(err) = self.release() {
+            log::warn!("Unable to release advisory lock: {err}");
+        }
+    }
+}
+
+pub(crate) fn xact_lock(connection: &crate::Connection, key: Key, mode: Mode) -> crate::Result {
+    let query = query("advisory_xact_lock", key, mode);
+
+    connection.query_one::<()>(&query, &key.params())
+}
+
+pub(crate) fn try_xact_lock(
+    connection: &crate::Connection,
+    key: Key,
+    mode: Mode,
+) -> crate::Result<bool> {
+    let query = query("try_advisory_xact_lock", key, mode);
+
+    connection.query_one::<bool>(&query, &key.params())
+}
+
+fn query(function: &str, key: Key, mode: Mode) -> String {
+    format!(
+        "select pg_{function}{}({})",
+        mode.suffix(),
+        key.placeholders()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Key, Mode};
+
+    #[test]
+    fn hash() {
+        assert_eq!(Key::hash(""), Key::Single(0xcbf2_9ce4_8422_2325_u64 as i64));
+        assert_eq!(Key::hash("job"), Key::hash("job"));
+        assert_ne!(Key::hash("job"), Key::hash("jobs"));
+    }
+
+    #[test]
+    fn query() {
+        assert_eq!(
+            super::query("advisory_lock", Key::Single(1), Mode::Exclusive),
+            "select pg_advisory_lock($1)"
+        );
+        assert_eq!(
+            super::query("try_advisory_xact_lock", Key::Pair(1, 2), Mode::Shared),
+            "select pg_try_advisory_xact_lock_shared($1, $2)"
+        );
+    }
+
+    #[test]
+    fn guard() -> crate::Result {
+        let conn1 = crate::Connection::new(&crate::test::dsn())?;
+        let conn2 = crate::Connection::new(&crate::test::dsn())?;
+        let key = Key::hash("elephantry::advisory_lock::test::guard");
+
+        let lock = conn1.advisory_lock(key, Mode::Exclusive)?;
+        assert!(conn2.try_advisory_lock(key, Mode::Exclusive)?.is_none());
+        assert!(conn2.try_advisory_lock(key, Mode::Shared)?.is_none());
+        drop(lock);
+
+        let lock = conn2.try_advisory_lock(key, Mode::Shared)?;
+        assert!(lock.is_some());
+        assert!(conn1.try_advisory_lock(key, Mode::Shared)?.is_some());
+        assert!(conn1.try_advisory_lock(key, Mode::Exclusive)?.is_none());
+        lock.unwrap().unlock()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn xact() -> crate::Result {
+        let conn1 = crate::Connection::new(&crate::test::dsn())?;
+        let conn2 = crate::Connection::new(&crate::test::dsn())?;
+        let key = Key::Pair(0x656c, 0x7861);
+
+        let transaction = conn1.transaction();
+        transaction.start()?;
+        conn1.advisory_xact_lock(key, Mode::Exclusive)?;
+        assert!(!conn2.try_advisory_xact_lock(key, Mode::Exclusive)?);
+        transaction.commit()?;
+
+        assert!(conn2.try_advisory_lock(key, Mode::Exclusive)?.is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+/**
+ * Stream of `COPY ... TO STDOUT` data created by the [`Async::copy_out`]
+ * method.
+ *
+ * Each item is a data row, as sent by the server. Dropping the stream before
+ * its end cancels the COPY.
+ *
+ * [`Async::copy_out`]: crate::Async::copy_out
+ */
+#[derive(Debug)]
+pub struct AsyncCopyOut<'c> {
+    r#async: super::Async<'c>,
+}
+
+impl<'c> AsyncCopyOut<'c> {
+    pub(crate) fn new(r#async: super::Async<'c>) -> Self {
+        Self { r#async }
+    }
+
+    fn poll_data(
+        &mut self,
+        connection: &libpq::Connection,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<crate::Result<Vec<u8>>>> {
+        use super::socket::CopyData;
+
+        let mut consumed = false;
+
+        loop {
+            match super::socket::get_copy_data(connection)? {
+                CopyData::Row(row) => return std::task::Poll::Ready(Some(Ok(row))),
+                // Retreives the COPY command result.
+                CopyData::Done => {
+                    return self
+                        .r#async
+                        .poll_result(connection, ctx)
+                        .map(|result| result.err().map(Err));
+                }
+                CopyData::Pending if !consumed => {
+                    super::socket::consume_input(connection)?;
+                    consumed = true;
+                }
+                CopyData::Pending => {
+                    std::task::ready!(self.r#async.socket(connection)?.poll_read_ready(ctx))?;
+                    consumed = false;
+                }
+            }
+        }
+    }
+}
+
+impl futures_core::Stream for AsyncCopyOut<'_> {
+    type Item = crate::Result<Vec<u8>>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if !this.r#async.pending {
+            return std::task::Poll::Ready(None);
+        }
+
+        let connection = match this.r#async.pq() {
+            Ok(connection) => connection,
+            Err(err) => return std::task::Poll::Ready(Some(Err(err))),
+        };
+
+        let poll = this.poll_data(&connection, ctx);
+
+        // The COPY can't be resumed after an error.
+        if matches!(poll, std::task::Poll::Ready(Some(Err(_)))) {
+            this.r#async.pending = false;
+        }
+
+        poll
+    }
+}
+
+/**
+ * Stream of entities created by the [`Async::copy_to`] method.
+ *
+ * [`Async::copy_to`]: crate::Async::copy_to
+ */
+#[derive(Debug)]
+pub struct AsyncCopyRows<'c, M: crate::Model> {
+    copy: AsyncCopyOut<'c>,
+    /**
+     * Empty result describing the fields.
+     */
+    description: crate::pq::Result,
+    header: bool,
+    marker: std::marker::PhantomData<fn() -> M>,
+}
+
+impl<'c, M: crate::Model> AsyncCopyRows<'c, M> {
+    pub(crate) fn new(copy: AsyncCopyOut<'c>, description: crate::pq::Result) -> Self {
+        Self {
+            copy,
+            description,
+            header: false,
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    /**
+     * Decodes a row of the binary COPY format, returns `None` for the
+     * trailer.
+     */
+    fn decode(&mut self, mut row: &[u8]) -> crate::Result<Option<Vec<Option<Vec<u8>>>>> {
+        if !self.header {
+            let header = crate::connection::COPY_SIGNATURE.len() + 4;
+
+            if !row.starts_with(crate::connection::COPY_SIGNATURE) || row.len() < header + 4 {
+                return Err(crate::Error::Parse("Invalid COPY header".to_string()));
+            }
+
+            let extension = crate::from_sql::read_i32(&mut &row[header..])? as usize;
+            row = row
+                .get(header + 4 + extension..)
+                .ok_or_else(|| crate::Error::Parse("Invalid COPY header".to_string()))?;
+            self.header = true;
+
+            // The header may be sent alone.
+            if row.is_empty() {
+                return Ok(Some(Vec::new()));
+            }
+        }
+
+        let count = crate::from_sql::read_i16(&mut row)?;
+
+        if count < 0 {
+            return Ok(None);
+        }
+
+        let mut values = Vec::with_capacity(count as usize);
+
+        for _ in 0..count {
+            let len = crate::from_sql::read_i32(&mut row)?;
+
+            if len < 0 {
+                values.push(None);
+                continue;
+            }
+
+            let len = len as usize;
+            let value = row
+                .get(..len)
+                .ok_or_else(|| crate::Error::Parse("Truncated COPY row".to_string()))?;
+            values.push(Some(value.to_vec()));
+            row = &row[len..];
+        }
+
+        Ok(Some(values))
+    }
+}
+
+impl<M: crate::Model> futures_core::Stream for AsyncCopyRows<'_, M> {
+    type Item = crate::Result<M::Entity>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let row = match std::task::ready!(std::pin::Pin::new(&mut this.copy).poll_next(ctx)) {
+                Some(Ok(row)) => row,
+                Some(Err(err)) => return std::task::Poll::Ready(Some(Err(err))),
+                None => return std::task::Poll::Ready(None),
+            };
+
+            match this.decode(&row)? {
+                // Header without row
+                Some(values) if values.is_empty() => (),
+                Some(values) => {
+                    let tuple = crate::Tuple::from_copy(&this.description, &values);
+
+                    return std::task::Poll::Ready(Some(Ok(M::create_entity(&tuple))));
+                }
+                // Trailer, the stream ends with the COPY result.
+                None => (),
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+/**
+ * Async lock serializing the async usages of a connection.
+ *
+ * Blocking methods of [`Connection`] only use the inner mutex, they are not
+ * serialized with async ones, but wait for the results of a dropped async
+ * query to be discarded.
+ *
+ * [`Connection`]: crate::Connection
+ */
+#[derive(Debug, Default)]
+pub(crate) struct Exclusive {
+    state: std::sync::Mutex<State>,
+    drained: std::sync::Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    locked: bool,
+    /**
+     * An async transaction was dropped without being committed or rolled
+     * back: the next user of the connection must roll it back.
+     */
+    dirty: bool,
+    /**
+     * The query of a dropped async handle is canceled in the background.
+     */
+    draining: bool,
+    waiters: Vec<std::task::Waker>,
+}
+
+impl Exclusive {
+    pub fn poll_lock(&self, ctx: &mut std::task::Context<'_>) -> std::task::Poll<crate::Result> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        if state.locked || state.draining {
+            state.wait(ctx);
+
+            std::task::Poll::Pending
+        } else {
+            state.locked = true;
+
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    /**
+     * Waits for the results of a dropped query to be discarded.
+     */
+    pub fn poll_drained(&self, ctx: &mut std::task::Context<'_>) -> std::task::Poll<crate::Result> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        if state.draining {
+            state.wait(ctx);
+
+            std::task::Poll::Pending
+        } else {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    /**
+     * Blocking version of [`Exclusive::poll_drained`].
+     */
+    pub fn wait_drained(&self) -> crate::Result {
+        let state = self
+            .state
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        self.drained
+            .wait_while(state, |x| x.draining)
+            .map(|_| ())
+            .map_err(|e| crate::Error::Mutex(e.to_string()))
+    }
+
+    pub fn set_draining(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.draining = true;
+        }
+    }
+
+    pub fn drained(&self) {
+        let waiters = match self.state.lock() {
+            Ok(mut state) => {
+                state.draining = false;
+                std::mem::take(&mut state.waiters)
+            }
+            Err(_) => return,
+        };
+
+        self.drained.notify_all();
+
+        for waiter in waiters {
+            waiter.wake();
+        }
+    }
+
+    pub fn set_dirty(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.dirty = true;
+        }
+    }
+
+    pub fn take_dirty(&self) -> bool {
+        self.state
+            .lock()
+            .map(|mut x| std::mem::take(&mut x.dirty))
+            .unwrap_or_default()
+    }
+
+    pub fn unlock(&self) {
+        let waiters = match self.state.lock() {
+            Ok(mut state) => {
+                state.locked = false;
+                std::mem::take(&mut state.waiters)
+            }
+            Err(_) => return,
+        };
+
+        for waiter in waiters {
+            waiter.wake();
+        }
+    }
+}
+
+impl State {
+    fn wait(&mut self, ctx: &std::task::Context<'_>) {
+        if !self.waiters.iter().any(|x| x.will_wake(ctx.waker())) {
+            self.waiters.push(ctx.waker().clone());
+        }
+    }
+}
@@ -0,0 +1,1646 @@
+use std::collections::HashMap;
+
+mod copy;
+mod exclusive;
+mod notifications;
+mod pool;
+mod rows;
+mod socket;
+mod timer;
+mod transaction;
+
+pub use copy::{AsyncCopyOut, AsyncCopyRows};
+pub(crate) use exclusive::Exclusive;
+pub use notifications::AsyncNotifications;
+#[cfg(any(feature = "bb8", feature = "deadpool"))]
+pub(crate) use pool::reset;
+pub use pool::{AsyncPool, AsyncPooled};
+pub use rows::AsyncRows;
+pub(crate) use socket::Registration;
+pub use transaction::AsyncTransaction;
+
+/**
+ * This `struct` is created by the [`Connection::async`] method.
+ *
+ * The future waits for the connection socket readiness. Enable the `tokio` or
+ * `async-io` (used by `smol` and `async-std`) feature to register it in the
+ * runtime reactor, otherwise a single helper thread waits for the sockets
+ * readiness.
+ *
+ * Async operations on the same connection are serialized.
+ *
+ * [`Connection::async`]: crate::Connection::async
+ */
+#[derive(Debug)]
+pub struct Async<'c> {
+    last_result: Option<crate::Result<crate::pq::Result>>,
+    connection: &'c crate::Connection,
+    mode: crate::pq::Format,
+    socket: Option<std::sync::Arc<socket::Socket>>,
+    lock: Lock,
+    pending: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Lock {
+    Released,
+    Held,
+    /**
+     * The lock is held by the current [`AsyncTransaction`].
+     */
+    Transaction,
+}
+
+impl std::future::Future for Async<'_> {
+    type Output = crate::Result<crate::pq::Result>;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let connection = match self.pq() {
+            Ok(connection) => connection,
+            Err(err) => return std::task::Poll::Ready(Err(err)),
+        };
+
+        self.poll_result(&connection, ctx)
+    }
+}
+
+impl Drop for Async<'_> {
+    fn drop(&mut self) {
+        if self.pending {
+            // The future was dropped before receiving its result: canceling
+            // the query blocks, the next users of the connection wait for it.
+            self.connection.exclusive.set_draining();
+
+            let drain = Drain {
+                connection: self.connection.connection.clone(),
+                exclusive: self.connection.exclusive.clone(),
+            };
+
+            spawn_blocking(move || drop(drain));
+        } else if let Ok(connection) = self.pq() {
+            connection.set_non_blocking(false).ok();
+        }
+
+        if self.lock == Lock::Held {
+            self.connection.exclusive.unlock();
+        }
+    }
+}
+
+impl<'c> Async<'c> {
+    pub(crate) fn new(connection: &'c crate::Connection) -> Self {
+        Self {
+            last_result: None,
+            connection,
+            mode: crate::pq::Format::Text,
+            socket: None,
+            lock: Lock::Released,
+            pending: false,
+        }
+    }
+
+    pub(crate) fn for_transaction(connection: &'c crate::Connection) -> Self {
+        let mut r#async = Self::new(connection);
+        r#async.lock = Lock::Transaction;
+
+        r#async
+    }
+
+    /**
+     * Creates a new handle on the same connection, to chain queries.
+     */
+    fn fork(&self) -> Self {
+        let mut fork = Self::new(self.connection);
+        fork.mode = self.mode;
+
+        if self.lock == Lock::Transaction {
+            fork.lock = Lock::Transaction;
+        }
+
+        fork
+    }
+
+    fn pq(&self) -> crate::Result<std::sync::MutexGuard<'c, libpq::Connection>> {
+        let connection: &'c crate::Connection = self.connection;
+
+        connection
+            .connection
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))
+    }
+
+    fn poll_result(
+        &mut self,
+        connection: &libpq::Connection,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<crate::Result<crate::pq::Result>> {
+        use std::task::Poll;
+
+        std::task::ready!(self.poll_flush(connection, ctx))?;
+
+        loop {
+            socket::consume_input(connection)?;
+
+            let mut copy = false;
+
+            while !connection.is_busy() {
+                match connection.result() {
+                    Some(result) => {
+                        copy = matches!(
+                            result.status(),
+                            libpq::Status::CopyIn
+                                | libpq::Status::CopyOut
+                                | libpq::Status::CopyBoth
+                        );
+
+                        self.last_result = Some(
+                            crate::pq::Result::try_from(result).map_err(|e| e.or_lost(connection)),
+                        );
+
+                        // libpq returns the same status until the end of the copy.
+                        if copy {
+                            break;
+                        }
+                    }
+                    None => break,
+                }
+            }
+
+            if !connection.is_busy() {
+                // The query is still in progress during a copy.
+                self.pending = copy;
+
+                return Poll::Ready(self.last_result.take().unwrap_or_else(|| {
+                    Err(crate::Error::Async(libpq::errors::Error::Backend(
+                        "No query in progress".to_string(),
+                    )))
+                }));
+            }
+
+            std::task::ready!(self.socket(connection)?.poll_read_ready(ctx))?;
+        }
+    }
+
+    /**
+     * Waits until all queued data are sent to the server.
+     */
+    fn poll_flush(
+        &mut self,
+        connection: &libpq::Connection,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<crate::Result> {
+        while !socket::flush(connection)? {
+            std::task::ready!(self.socket(connection)?.poll_write_ready(ctx))?;
+            // Reading may be required to unblock the server.
+            socket::consume_input(connection)?;
+        }
+
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    /**
+     * Waits for the exclusive usage of the connection.
+     */
+    async fn lock(&mut self) -> crate::Result {
+        match self.lock {
+            Lock::Released => {
+                std::future::poll_fn(|ctx| self.connection.exclusive.poll_lock(ctx)).await?;
+                self.lock = Lock::Held;
+
+                if self.connection.exclusive.take_dirty() {
+                    self.rollback_dropped().await?;
+                }
+            }
+            // A previous query of the transaction may have been dropped.
+            Lock::Transaction => {
+                std::future::poll_fn(|ctx| self.connection.exclusive.poll_drained(ctx)).await?;
+            }
+            Lock::Held => (),
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Rolls back the transaction left by a dropped [`AsyncTransaction`].
+     */
+    async fn rollback_dropped(&mut self) -> crate::Result {
+        let rollback = crate::transaction::rollback_query(None);
+
+        if self.pq()?.transaction_status() != libpq::transaction::Status::Idle {
+            self.start(|connection| connection.send_query(&rollback))?;
+            (&mut *self).await?;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Sends `query` in non-blocking mode with `f`, the blocking mode is
+     * restored when this handle is dropped.
+     */
+    async fn send<F>(&mut self, query: &str, f: F) -> crate::Result
+    where
+        F: FnOnce(&libpq::Connection) -> libpq::errors::Result,
+    {
+        self.lock().await?;
+        self.connection.track_write(query);
+        self.start(f)
+    }
+
+    fn start<F>(&mut self, f: F) -> crate::Result
+    where
+        F: FnOnce(&libpq::Connection) -> libpq::errors::Result,
+    {
+        let connection = self.pq()?;
+
+        connection
+            .set_non_blocking(true)
+            .and_then(|_| f(&connection))
+            .map_err(|err| {
+                connection.set_non_blocking(false).ok();
+                crate::Error::Async(err).or_lost(&connection)
+            })?;
+
+        self.pending = true;
+
+        Ok(())
+    }
+
+    /**
+     * Waits until the output buffer accepts `f` data.
+     */
+    async fn put<F>(&mut self, f: F) -> crate::Result
+    where
+        F: Fn(&libpq::Connection) -> crate::Result<bool>,
+    {
+        std::future::poll_fn(|ctx| {
+            let connection = self.pq()?;
+
+            while !f(&connection)? {
+                std::task::ready!(self.poll_flush(&connection, ctx))?;
+            }
+
+            std::task::Poll::Ready(Ok(()))
+        })
+        .await
+    }
+
+    fn socket(&mut self, connection: &libpq::Connection) -> crate::Result<&socket::Socket> {
+        if self.socket.is_none() {
+            self.socket = Some(self.connection.registration.socket(connection)?);
+        }
+
+        Ok(self.socket.as_ref().unwrap())
+    }
+
+    /**
+     * Async version of [`Connection::execute`].
+     *
+     * [`Connection::execute`]: crate::Connection::execute
+     */
+    pub async fn execute(mut self, query: &str) -> crate::Result<crate::pq::Result> {
+        self.send(query, |connection| connection.send_query(query))
+            .await?;
+
+        self.await
+    }
+
+    /**
+     * Async version of [`Connection::query`].
+     *
+     * [`Connection::query`]: crate::Connection::query
+     */
+    pub async fn query<E: crate::Entity>(
+        self,
+        query: &str,
+        params: &[&(dyn crate::ToSql + Sync)],
+    ) -> crate::Result<crate::Rows<E>> {
+        let param = self.params(params)?;
+
+        Ok(self.send_query(query, param).await?.into())
+    }
+
+    /**
+     * Async version of [`Connection::query_one`].
+     *
+     * [`Connection::query_one`]: crate::Connection::query_one
+     */
+    pub async fn query_one<E: crate::Entity>(
+        self,
+        query: &str,
+        params: &[&(dyn crate::ToSql + Sync)],
+    ) -> crate::Result<E> {
+        match self.query(query, params).await?.try_get(0) {
+            Some(e) => Ok(e),
+            None => Err(crate::Error::MissingField("0".to_string())),
+        }
+    }
+
+    /**
+     * Streams the rows of `query`, see [`AsyncRows`].
+     *
+     * ```no_run
+     * # fn main() -> elephantry::Result {
+     * # smol::block_on(async {
+     * use smol::stream::StreamExt as _;
+     *
+     * let connection = elephantry::Connection::new("")?;
+     * let mut rows = connection
+     *     .r#async()
+     *     .query_stream::<i32>("select generate_series(1, 1000000)", &[])
+     *     .await?;
+     *
+     * while let Some(row) = rows.next().await {
+     *     println!("{}", row?);
+     * }
+     * # Ok(())
+     * # })
+     * # }
+     * ```
+     */
+    pub async fn query_stream<E: crate::Entity>(
+        mut self,
+        query: &str,
+        params: &[&(dyn crate::ToSql + Sync)],
+    ) -> crate::Result<AsyncRows<'c, E>> {
+        let param = self.params(params)?;
+        self.send_params(query, param, true).await?;
+
+        Ok(AsyncRows::new(self))
+    }
+
+    /**
+     * Streams the notifications received by the connection, see
+     * [`AsyncNotifications`].
+     */
+    pub async fn notifications(mut self) -> crate::Result<AsyncNotifications<'c>> {
+        self.lock().await?;
+        self.pq()?
+            .set_non_blocking(true)
+            .map_err(crate::Error::Async)?;
+
+        Ok(AsyncNotifications::new(self))
+    }
+
+    /**
+     * Async version of [`Connection::find_by_pk`].
+     *
+     * [`Connection::find_by_pk`]: crate::Connection::find_by_pk
+     */
+    pub async fn find_by_pk<M>(
+        self,
+        pk: &HashMap<&str, &(impl crate::ToSql + ?Sized)>,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let (clause, param) = self.pk_params::<M, _>(pk)?;
+        let query = crate::Connection::find_where_query::<M>(&clause, None, None);
+        let mut tuples: crate::Rows<M::Entity> = self.send_query(&query, param).await?.into();
+
+        Ok(tuples.next())
+    }
+
+    /**
+     * Async version of [`Connection::find_all`].
+     *
+     * [`Connection::find_all`]: crate::Connection::find_all
+     */
+    pub async fn find_all<M>(self, suffix: Option<&str>) -> crate::Result<crate::Rows<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        self.find_where::<M>("true", &[], suffix).await
+    }
+
+    /**
+     * Async version of [`Connection::find_where`].
+     *
+     * [`Connection::find_where`]: crate::Connection::find_where
+     */
+    pub async fn find_where<M>(
+        self,
+        clause: &str,
+        params: &[&(dyn crate::ToSql + Sync)],
+        suffix: Option<&str>,
+    ) -> crate::Result<crate::Rows<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let query = crate::Connection::find_where_query::<M>(clause, suffix, None);
+
+        self.query(&query, params).await
+    }
+
+    /**
+     * Async version of [`Connection::paginate_find_where`].
+     *
+     * [`Connection::paginate_find_where`]: crate::Connection::paginate_find_where
+     */
+    pub async fn paginate_find_where<M>(
+        self,
+        clause: &str,
+        params: &[&(dyn crate::ToSql + Sync)],
+        max_per_page: usize,
+        page: usize,
+        suffix: Option<&str>,
+    ) -> crate::Result<crate::Pager<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let suffix = crate::Connection::paginate_suffix(suffix, max_per_page, page);
+
+        let rows = self
+            .fork()
+            .find_where::<M>(clause, params, Some(&suffix))
+            .await?;
+        let count = self.count_where::<M>(clause, params).await?;
+
+        let pager = crate::Pager::new(rows, count, page, max_per_page);
+
+        Ok(pager)
+    }
+
+    /**
+     * Async version of [`Connection::count_where`].
+     *
+     * [`Connection::count_where`]: crate::Connection::count_where
+     */
+    pub async fn count_where<M>(
+        self,
+        clause: &str,
+        params: &[&(dyn crate::ToSql + Sync)],
+    ) -> crate::Result<usize>
+    where
+        M: crate::Model,
+    {
+        let query = crate::Connection::count_where_query::<M>(clause);
+        let param = self.params(params)?;
+        let results = self.send_query(&query, param).await?;
+
+        results.get(0).try_get("count")
+    }
+
+    /**
+     * Async version of [`Connection::exist_where`].
+     *
+     * [`Connection::exist_where`]: crate::Connection::exist_where
+     */
+    pub async fn exist_where<M>(
+        self,
+        clause: &str,
+        params: &[&(dyn crate::ToSql + Sync)],
+    ) -> crate::Result<bool>
+    where
+        M: crate::Model,
+    {
+        let query = crate::Connection::exist_where_query::<M>(clause);
+        let param = self.params(params)?;
+        let results = self.send_query(&query, param).await?;
+
+        results.get(0).try_get("result")
+    }
+
+    /**
+     * Async version of [`Connection::exist_by_pk`].
+     *
+     * [`Connection::exist_by_pk`]: crate::Connection::exist_by_pk
+     */
+    pub async fn exist_by_pk<M>(
+        self,
+        pk: &HashMap<&str, &(impl crate::ToSql + ?Sized)>,
+    ) -> crate::Result<bool>
+    where
+        M: crate::Model,
+    {
+        let (clause, param) = self.pk_params::<M, _>(pk)?;
+        let query = crate::Connection::exist_where_query::<M>(&clause);
+        let results = self.send_query(&query, param).await?;
+
+        results.get(0).try_get("result")
+    }
+
+    /**
+     * Async version of [`Connection::exist_one`].
+     *
+     * [`Connection::exist_one`]: crate::Connection::exist_one
+     */
+    pub async fn exist_one<M>(self, entity: &M::Entity) -> crate::Result<bool>
+    where
+        M: crate::Model,
+    {
+        let (clause, param) = self.pk_params::<M, _>(&M::primary_key(entity)?)?;
+        let query = crate::Connection::exist_where_query::<M>(&clause);
+        let results = self.send_query(&query, param).await?;
+
+        results.get(0).try_get("result")
+    }
+
+    /**
+     * Async version of [`Connection::insert_one`].
+     *
+     * [`Connection::insert_one`]: crate::Connection::insert_one
+     */
+    pub async fn insert_one<M>(self, entity: &M::Entity) -> crate::Result<M::Entity>
+    where
+        M: crate::Model,
+    {
+        self.insert::<M>(entity, None).await.map(Option::unwrap)
+    }
+
+    /**
+     * Async version of [`Connection::upsert_one`].
+     *
+     * [`Connection::upsert_one`]: crate::Connection::upsert_one
+     */
+    pub async fn upsert_one<M>(
+        self,
+        entity: &M::Entity,
+        target: &str,
+        action: &str,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let suffix = format!("on conflict {target} do {action}");
+        self.insert::<M>(entity, Some(suffix.as_str())).await
+    }
+
+    async fn insert<M>(
+        self,
+        entity: &M::Entity,
+        suffix: Option<&str>,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let (query, param) = {
+            let (query, params) = crate::Connection::insert_query::<M>(entity, suffix);
+
+            (query, self.params(&params)?)
+        };
+        let results = self.send_query(&query, param).await?;
+        let result = results.try_get(0).map(|x| M::create_entity(&x));
+
+        Ok(result)
+    }
+
+    /**
+     * Async version of [`Connection::update_one`].
+     *
+     * [`Connection::update_one`]: crate::Connection::update_one
+     */
+    pub async fn update_one<M>(
+        self,
+        pk: &HashMap<&str, &(impl crate::ToSql + ?Sized)>,
+        entity: &M::Entity,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let update = {
+            let data = crate::Connection::entity_data::<M>(entity);
+
+            self.update_params::<M, _, _>(pk, &data)?
+        };
+
+        self.update::<M>(update).await
+    }
+
+    /**
+     * Async version of [`Connection::update_by_pk`].
+     *
+     * [`Connection::update_by_pk`]: crate::Connection::update_by_pk
+     */
+    pub async fn update_by_pk<M>(
+        self,
+        pk: &HashMap<&str, &(impl crate::ToSql + ?Sized)>,
+        data: &HashMap<String, &(impl crate::ToSql + ?Sized)>,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let update = self.update_params::<M, _, _>(pk, data)?;
+
+        self.update::<M>(update).await
+    }
+
+    fn update_params<M, P, D>(
+        &self,
+        pk: &HashMap<&str, &P>,
+        data: &HashMap<String, &D>,
+    ) -> crate::Result<Option<(String, crate::connection::Param)>>
+    where
+        M: crate::Model,
+        P: crate::ToSql + ?Sized,
+        D: crate::ToSql + ?Sized,
+    {
+        let Some((query, params, data)) =
+            crate::Connection::update_by_pk_query::<M, _, _>(pk, data)?
+        else {
+            return Ok(None);
+        };
+
+        let mut param = self.params(&params)?;
+        param.extend(self.params(&data)?);
+
+        Ok(Some((query, param)))
+    }
+
+    async fn update<M>(
+        self,
+        update: Option<(String, crate::connection::Param)>,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let Some((query, param)) = update else {
+            return Ok(None);
+        };
+
+        let results = self.send_query(&query, param).await?;
+
+        let entity = results.try_get(0).map(|x| M::create_entity(&x));
+
+        Ok(entity)
+    }
+
+    /**
+     * Async version of [`Connection::delete_one`].
+     *
+     * [`Connection::delete_one`]: crate::Connection::delete_one
+     */
+    pub async fn delete_one<M>(self, entity: &M::Entity) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let (clause, param) = self.pk_params::<M, _>(&M::primary_key(entity)?)?;
+
+        self.delete::<M>(&clause, param).await
+    }
+
+    /**
+     * Async version of [`Connection::delete_by_pk`].
+     *
+     * [`Connection::delete_by_pk`]: crate::Connection::delete_by_pk
+     */
+    pub async fn delete_by_pk<M>(
+        self,
+        pk: &HashMap<&str, &(impl crate::ToSql + ?Sized)>,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let (clause, param) = self.pk_params::<M, _>(pk)?;
+
+        self.delete::<M>(&clause, param).await
+    }
+
+    async fn delete<M>(
+        self,
+        clause: &str,
+        param: crate::connection::Param,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let query = crate::Connection::delete_where_query::<M>(clause);
+        let mut results: crate::Rows<M::Entity> = self.send_query(&query, param).await?.into();
+
+        Ok(results.next())
+    }
+
+    /**
+     * Async version of [`Connection::delete_where`].
+     *
+     * [`Connection::delete_where`]: crate::Connection::delete_where
+     */
+    pub async fn delete_where<M>(
+        self,
+        clause: &str,
+        params: &[&(dyn crate::ToSql + Sync)],
+    ) -> crate::Result<crate::Rows<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let query = crate::Connection::delete_where_query::<M>(clause);
+
+        self.query(&query, params).await
+    }
+
+    /**
+     * Async version of [`Connection::copy`].
+     *
+     * Entities are encoded and sent by chunks, waiting for the server to
+     * consume the previous ones.
+     *
+     * [`Connection::copy`]: crate::Connection::copy
+     */
+    pub async fn copy<M, I>(mut self, entities: I) -> crate::Result
+    where
+        I: Iterator<Item = M::Entity>,
+        M: crate::Model,
+    {
+        let field_names = M::default_projection().field_names();
+        let query = crate::Connection::copy_query::<M>(&field_names);
+        self.send(&query, |connection| connection.send_query(&query))
+            .await?;
+        (&mut self).await?;
+
+        let mut buf = crate::Connection::copy_header()?;
+
+        for entity in entities {
+            crate::Connection::copy_row(&mut buf, &entity, &field_names)?;
+
+            if buf.len() >= COPY_CHUNK_SIZE {
+                self.put(|connection| socket::put_copy_data(connection, &buf))
+                    .await?;
+                buf.clear();
+            }
+        }
+
+        crate::Connection::copy_trailer(&mut buf)?;
+        self.put(|connection| socket::put_copy_data(connection, &buf))
+            .await?;
+        self.put(|connection| socket::put_copy_end(connection, None))
+            .await?;
+
+        self.await.map(|_| ())
+    }
+
+    /**
+     * Sends the data read from `reader` to a `COPY ... FROM STDIN` query.
+     *
+     * If reading fails, the COPY is aborted and the error returned.
+     */
+    pub async fn copy_in<R>(mut self, query: &str, mut reader: R) -> crate::Result
+    where
+        R: futures_io::AsyncRead + Unpin,
+    {
+        self.send(query, |connection| connection.send_query(query))
+            .await?;
+        (&mut self).await?;
+
+        let mut buf = vec![0; COPY_CHUNK_SIZE];
+
+        loop {
+            let read = std::future::poll_fn(|ctx| {
+                std::pin::Pin::new(&mut reader).poll_read(ctx, &mut buf)
+            })
+            .await;
+
+            match read {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.put(|connection| socket::put_copy_data(connection, &buf[..n]))
+                        .await?
+                }
+                Err(err) => {
+                    let message = std::ffi::CString::new(err.to_string()).unwrap_or_default();
+                    self.put(|connection| socket::put_copy_end(connection, Some(&message)))
+                        .await?;
+                    // The server reports the COPY failure with our message.
+                    (&mut self).await.ok();
+
+                    return Err(err.into());
+                }
+            }
+        }
+
+        self.put(|connection| socket::put_copy_end(connection, None))
+            .await?;
+
+        self.await.map(|_| ())
+    }
+
+    /**
+     * Streams the data of a `COPY ... TO STDOUT` query, see [`AsyncCopyOut`].
+     */
+    pub async fn copy_out(mut self, query: &str) -> crate::Result<AsyncCopyOut<'c>> {
+        self.send(query, |connection| connection.send_query(query))
+            .await?;
+        (&mut self).await?;
+
+        Ok(AsyncCopyOut::new(self))
+    }
+
+    /**
+     * Streams the entities matching `clause` via `COPY ... TO STDOUT`.
+     *
+     * COPY doesn’t accept parameters: values in `clause` must be escaped.
+     */
+    pub async fn copy_to<M>(self, clause: &str) -> crate::Result<AsyncCopyRows<'c, M>>
+    where
+        M: crate::Model,
+    {
+        let description = crate::Connection::find_where_query::<M>("false", None, None);
+        let description = self
+            .fork()
+            .send_query(&description, crate::connection::Param::default())
+            .await?;
+
+        let query = crate::Connection::find_where_query::<M>(clause, None, None);
+        let query = format!(
+            "copy ({}) to stdout (format binary)",
+            query.trim_end().trim_end_matches(';')
+        );
+        let copy = self.copy_out(&query).await?;
+
+        Ok(AsyncCopyRows::new(copy, description))
+    }
+
+    /**
+     * Renders the parameters before the first await, the future doesn’t hold
+     * them.
+     */
+    fn params<P: crate::ToSql + ?Sized>(
+        &self,
+        params: &[&P],
+    ) -> crate::Result<crate::connection::Param> {
+        crate::Connection::transform_params(self.mode, params)
+    }
+
+    fn pk_params<M, P>(
+        &self,
+        pk: &HashMap<&str, &P>,
+    ) -> crate::Result<(String, crate::connection::Param)>
+    where
+        M: crate::Model,
+        P: crate::ToSql + ?Sized,
+    {
+        let (clause, params) = crate::Connection::pk_clause::<M, _>(pk)?;
+
+        Ok((clause, self.params(&params)?))
+    }
+
+    async fn send_query(
+        mut self,
+        query: &str,
+        param: crate::connection::Param,
+    ) -> crate::Result<crate::pq::Result> {
+        self.send_params(query, param, false).await?;
+
+        self.await
+    }
+
+    async fn send_params(
+        &mut self,
+        query: &str,
+        param: crate::connection::Param,
+        single_row: bool,
+    ) -> crate::Result {
+        let query = crate::Connection::order_parameters(query);
+
+        self.send(&query, |connection| {
+            connection.send_query_params(
+                &query,
+                &param.types,
+                &param.values(),
+                &param.formats,
+                crate::pq::Format::Binary,
+            )?;
+
+            if single_row {
+                connection.set_single_row_mode()?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    /**
+     * Starts a transaction. The connection is exclusively held by the
+     * returned handle until it is committed, rolled back or dropped.
+     */
+    pub async fn transaction(mut self) -> crate::Result<AsyncTransaction<'c>> {
+        if self.lock == Lock::Transaction {
+            return Err(crate::Error::Async(libpq::errors::Error::Backend(
+                "A transaction is already in progress".to_string(),
+            )));
+        }
+
+        self.send("begin transaction", |connection| {
+            connection.send_query("begin transaction")
+        })
+        .await?;
+        (&mut self).await?;
+
+        // The lock is now owned by the transaction.
+        self.lock = Lock::Transaction;
+
+        Ok(AsyncTransaction::new(self.connection))
+    }
+
+    /**
+     * Runs `f` in a transaction. The transaction is committed if `f` returns
+     * `Ok`, rolled back if it returns `Err` or if the future is dropped
+     * before its completion.
+     *
+     * ```no_run
+     * # fn main() -> elephantry::Result {
+     * # smol::block_on(async {
+     * let connection = elephantry::Connection::new("")?;
+     *
+     * let id = connection
+     *     .r#async()
+     *     .in_transaction(async |transaction| {
+     *         transaction.r#async().execute("delete from event").await?;
+     *         transaction.r#async().query_one::<i32>("select 1", &[]).await
+     *     })
+     *     .await?;
+     * # Ok(())
+     * # })
+     * # }
+     * ```
+     */
+    pub async fn in_transaction<F, T>(self, f: F) -> crate::Result<T>
+    where
+        F: AsyncFnOnce(&AsyncTransaction<'c>) -> crate::Result<T>,
+    {
+        let transaction = self.transaction().await?;
+
+        match f(&transaction).await {
+            Ok(value) => {
+                transaction.commit().await?;
+                Ok(value)
+            }
+            Err(err) => {
+                transaction.rollback().await?;
+                Err(err)
+            }
+        }
+    }
+}
+
+/**
+ * Size of the COPY data sent at once.
+ */
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/**
+ * Drives the connection establishment, `poll` is `libpq::Connection::poll`
+ * or `libpq::Connection::reset_poll`.
+ */
+pub(crate) async fn connect(
+    connection: libpq::Connection,
+    poll: fn(&libpq::Connection) -> libpq::poll::Status,
+) -> crate::Result<libpq::Connection> {
+    use std::task::Poll;
+
+    let mut connection = Some(connection);
+    // libpq documentation: behave as if `poll` last returned `Writing`.
+    let mut status = libpq::poll::Status::Writing;
+    let mut socket: Option<(i32, socket::Socket)> = None;
+
+    std::future::poll_fn(move |ctx| {
+        let Some(conn) = connection.as_ref() else {
+            return Poll::Ready(Err(crate::Error::Async(libpq::errors::Error::Unknow)));
+        };
+
+        loop {
+            match status {
+                libpq::poll::Status::Ok => return Poll::Ready(Ok(connection.take().unwrap())),
+                libpq::poll::Status::Failed => {
+                    return Poll::Ready(Err(crate::Error::Async(socket::error(conn))));
+                }
+                libpq::poll::Status::Active => (),
+                libpq::poll::Status::Reading | libpq::poll::Status::Writing => {
+                    // The socket may change, for example when trying the next host.
+                    let fd = conn.socket()?;
+
+                    if socket.as_ref().map(|(x, _)| *x) != Some(fd) {
+                        socket = None;
+                        socket = Some((fd, socket::Socket::new(conn)?));
+                    }
+
+                    let (_, socket) = socket.as_ref().unwrap();
+
+                    let ready = if status == libpq::poll::Status::Reading {
+                        socket.poll_read_ready(ctx)
+                    } else {
+                        socket.poll_write_ready(ctx)
+                    };
+
+                    std::task::ready!(ready)?;
+                }
+            }
+
+            status = poll(conn);
+        }
+    })
+    .await
+}
+
+/**
+ * Cancels the query of a dropped handle when dropped, so it also runs if the
+ * blocking pool is shut down before starting it.
+ */
+struct Drain {
+    connection: std::sync::Arc<std::sync::Mutex<libpq::Connection>>,
+    exclusive: std::sync::Arc<Exclusive>,
+}
+
+impl Drop for Drain {
+    fn drop(&mut self) {
+        if let Ok(connection) = self.connection.lock() {
+            cancel(&connection);
+        }
+
+        self.exclusive.drained();
+    }
+}
+
+/**
+ * Runs `f` in the blocking pool of the runtime, or in a new thread.
+ */
+fn spawn_blocking(f: impl FnOnce() + Send + 'static) {
+    #[cfg(feature = "tokio")]
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        handle.spawn_blocking(f);
+        return;
+    }
+
+    #[cfg(feature = "async-io")]
+    blocking::unblock(f).detach();
+
+    #[cfg(not(feature = "async-io"))]
+    std::thread::spawn(f);
+}
+
+/**
+ * Cancels the current query and discards its results.
+ */
+fn cancel(connection: &libpq::Connection) {
+    let mut canceled = false;
+    let mut stop = || {
+        if !std::mem::replace(&mut canceled, true) {
+            connection.cancel().request().ok();
+        }
+    };
+
+    connection.consume_input().ok();
+
+    if connection.is_busy() {
+        stop();
+    }
+
+    connection.set_non_blocking(false).ok();
+
+    // libpq isn't busy while a row or copy data are available in single-row
+    // or copy mode: the server must also be stopped before draining them.
+    while let Some(result) = connection.result() {
+        match result.status() {
+            libpq::Status::CopyIn => {
+                connection.put_copy_end(Some("canceled")).ok();
+            }
+            libpq::Status::CopyOut => {
+                stop();
+                while connection.copy_data(false).is_ok() {}
+            }
+            libpq::Status::SingleTuple => stop(),
+            libpq::Status::CopyBoth => break,
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[derive(Clone, Debug, PartialEq, elephantry_derive::Entity)]
+    #[elephantry(model = "Model", structure = "Structure", relation = "async_test")]
+    pub struct Entity {
+        #[elephantry(pk)]
+        pub id: i32,
+        pub name: String,
+    }
+
+    #[test]
+    fn model() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        conn.execute("create temporary table async_test (id int primary key, name text)")?;
+
+        smol::block_on(async {
+            let mut entity = Entity {
+                id: 1,
+                name: "first".to_string(),
+            };
+
+            let first = conn.r#async().insert_one::<Model>(&entity).await?;
+            assert_eq!(first, entity);
+
+            let upserted = conn
+                .r#async()
+                .upsert_one::<Model>(&entity, "(id)", "nothing")
+                .await?;
+            assert!(upserted.is_none());
+
+            entity.id = 2;
+            conn.r#async().insert_one::<Model>(&entity).await?;
+
+            let found = conn
+                .r#async()
+                .find_by_pk::<Model>(&crate::pk!(id => 1))
+                .await?;
+            assert_eq!(found.as_ref(), Some(&first));
+
+            let updated = conn
+                .r#async()
+                .update_by_pk::<Model>(&crate::pk!(id => 2), &crate::values!(name => "second"))
+                .await?;
+            assert_eq!(updated.map(|x| x.name), Some("second".to_string()));
+
+            let rows = conn
+                .r#async()
+                .find_where::<Model>("name = $*", &[&"second"], None)
+                .await?;
+            assert_eq!(rows.len(), 1);
+
+            assert_eq!(conn.r#async().count_where::<Model>("true", &[]).await?, 2);
+            assert!(conn.r#async().exist_one::<Model>(&first).await?);
+
+            conn.r#async()
+                .copy::<Model, _>((3..=10).map(|id| Entity {
+                    id,
+                    name: format!("copy {id}"),
+                }))
+                .await?;
+
+            let pager = conn
+                .r#async()
+                .paginate_find_where::<Model>("id > $*", &[&2], 5, 2, Some("order by id"))
+                .await?;
+            assert_eq!(pager.count(), 8);
+            assert_eq!(
+                pager.into_iter().map(|x| x.id).collect::<Vec<_>>(),
+                vec![8, 9, 10]
+            );
+
+            let deleted = conn
+                .r#async()
+                .delete_where::<Model>("id > $*", &[&2])
+                .await?;
+            assert_eq!(deleted.len(), 8);
+
+            assert!(conn.r#async().delete_one::<Model>(&first).await?.is_some());
+            assert_eq!(conn.r#async().find_all::<Model>(None).await?.len(), 1);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn concurrent() -> crate::Result {
+        let conn1 = crate::Connection::new(&crate::test::dsn())?;
+        let conn2 = crate::Connection::new(&crate::test::dsn())?;
+        let start = std::time::Instant::now();
+
+        let (a, b) = smol::block_on(smol::future::zip(
+            conn1
+                .r#async()
+                .query_one::<i32>("select 1 from pg_sleep(0.5)", &[]),
+            conn2
+                .r#async()
+                .query_one::<i32>("select 2 from pg_sleep(0.5)", &[]),
+        ));
+
+        assert_eq!(a?, 1);
+        assert_eq!(b?, 2);
+        assert!(start.elapsed() < std::time::Duration::from_millis(900));
+
+        Ok(())
+    }
+
+    #[test]
+    fn multiple_results() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        let result = smol::block_on(conn.r#async().execute("select 1; select 2"))?;
+
+        assert_eq!(result.get(0).get::<i32>("?column?"), 2);
+        assert_eq!(conn.query_one::<i32>("select 3", &[])?, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream() -> crate::Result {
+        use smol::stream::StreamExt as _;
+
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+
+        smol::block_on(async {
+            let rows = conn
+                .r#async()
+                .query_stream::<i32>("select generate_series(1, $*)", &[&10])
+                .await?;
+            let rows = rows.try_collect::<_, _, Vec<_>>().await?;
+            assert_eq!(rows, (1..=10).collect::<Vec<_>>());
+
+            let mut rows = conn
+                .r#async()
+                .query_stream::<i32>("select generate_series(1, 10000000)", &[])
+                .await?;
+            assert_eq!(rows.next().await.transpose()?, Some(1));
+            drop(rows);
+
+            assert_eq!(conn.r#async().query_one::<i32>("select 2", &[]).await?, 2);
+
+            let mut rows = conn
+                .r#async()
+                .query_stream::<i32>("select 1 / 0", &[])
+                .await?;
+            assert!(matches!(rows.next().await, Some(Err(crate::Error::Sql(_)))));
+            assert!(rows.next().await.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn notifications() -> crate::Result {
+        use smol::stream::StreamExt as _;
+
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        let other = crate::Connection::new(&crate::test::dsn())?;
+
+        smol::block_on(async {
+            let mut notifications = conn.r#async().notifications().await?;
+            notifications.listen("async_first").await?;
+
+            other.notify("async_first", Some("payload"))?;
+            let notify = notifications.next().await.transpose()?.unwrap();
+            assert_eq!(notify.relname, "async_first");
+            assert_eq!(notify.extra, "payload");
+
+            notifications.unlisten("async_first").await?;
+            notifications.listen("async_second").await?;
+
+            other.notify("async_first", None)?;
+            other.notify("async_second", None)?;
+            let notify = notifications.next().await.transpose()?.unwrap();
+            assert_eq!(notify.relname, "async_second");
+
+            Ok(())
+        })
+    }
+
+    struct FailingReader;
+
+    impl futures_io::AsyncRead for FailingReader {
+        fn poll_read(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+            _: &mut [u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            std::task::Poll::Ready(Err(std::io::Error::other("read failed")))
+        }
+    }
+
+    #[test]
+    fn copy() -> crate::Result {
+        use smol::stream::StreamExt as _;
+
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        conn.execute("create temporary table async_test (id int primary key, name text)")?;
+
+        smol::block_on(async {
+            conn.r#async()
+                .copy::<Model, _>((1..=10_000).map(|id| Entity {
+                    id,
+                    name: format!("copy {id}"),
+                }))
+                .await?;
+
+            let reader = smol::io::Cursor::new(b"10001\tfirst\n10002\tsecond\n");
+            conn.r#async()
+                .copy_in("copy async_test from stdin", reader)
+                .await?;
+            assert_eq!(
+                conn.r#async().count_where::<Model>("true", &[]).await?,
+                10_002
+            );
+
+            let data = conn
+                .r#async()
+                .copy_out("copy (select * from async_test where id > 10000 order by id) to stdout")
+                .await?
+                .try_collect::<_, _, Vec<_>>()
+                .await?;
+            assert_eq!(
+                data,
+                vec![b"10001\tfirst\n".to_vec(), b"10002\tsecond\n".to_vec()]
+            );
+
+            let entities = conn
+                .r#async()
+                .copy_to::<Model>("id >= 9999 order by id")
+                .await?
+                .try_collect::<_, _, Vec<_>>()
+                .await?;
+            assert_eq!(
+                entities.into_iter().map(|x| x.name).collect::<Vec<_>>(),
+                vec!["copy 9999", "copy 10000", "first", "second"]
+            );
+
+            let mut copy = conn
+                .r#async()
+                .copy_out("copy (select generate_series(1, 10000000)) to stdout")
+                .await?;
+            assert_eq!(copy.next().await.transpose()?, Some(b"1\n".to_vec()));
+            drop(copy);
+
+            let result = conn
+                .r#async()
+                .copy_out("copy (select 1 / 0) to stdout")
+                .await;
+            assert!(result.is_err());
+
+            let result = conn
+                .r#async()
+                .copy_in("copy async_test from stdin", FailingReader)
+                .await;
+            assert!(matches!(result, Err(crate::Error::Io(_))));
+            assert_eq!(
+                conn.r#async().count_where::<Model>("true", &[]).await?,
+                10_002
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn transaction() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        conn.execute("create temporary table async_test (id int primary key, name text)")?;
+
+        smol::block_on(async {
+            let id = conn
+                .r#async()
+                .in_transaction(async |transaction| {
+                    transaction
+                        .set_isolation_level(crate::transaction::IsolationLevel::Serializable)
+                        .await?;
+                    transaction
+                        .r#async()
+                        .execute("insert into async_test values (1, 'commit')")
+                        .await?;
+                    transaction.set_save_point("before").await?;
+                    transaction
+                        .r#async()
+                        .execute("insert into async_test values (2, 'savepoint')")
+                        .await?;
+                    transaction.rollback_to("before").await?;
+                    assert_eq!(transaction.is_transaction_ok()?, Some(true));
+
+                    transaction
+                        .r#async()
+                        .query_one::<i32>("select max(id) from async_test", &[])
+                        .await
+                })
+                .await?;
+            assert_eq!(id, 1);
+
+            let result = conn
+                .r#async()
+                .in_transaction(async |transaction| {
+                    transaction
+                        .r#async()
+                        .execute("insert into async_test values (3, 'rollback')")
+                        .await?;
+                    transaction.r#async().execute("select 1/0").await
+                })
+                .await;
+            assert!(result.is_err());
+
+            // Cancellation
+            let future = conn.r#async().in_transaction(async |transaction| {
+                transaction
+                    .r#async()
+                    .execute("insert into async_test values (4, 'cancel')")
+                    .await?;
+                transaction.r#async().execute("select pg_sleep(10)").await
+            });
+            let start = std::time::Instant::now();
+            let timeout = async {
+                smol::Timer::after(std::time::Duration::from_millis(200)).await;
+                Err(crate::Error::NotInTransaction)
+            };
+            assert!(smol::future::or(future, timeout).await.is_err());
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+            let ids = conn
+                .r#async()
+                .query::<i32>("select id from async_test", &[])
+                .await?;
+            assert_eq!(ids.collect::<Vec<_>>(), vec![1]);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn drop_transaction() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        conn.execute("create temporary table async_drop (id int)")?;
+
+        smol::block_on(async {
+            let count = async || {
+                conn.r#async()
+                    .query_one::<i64>("select count(*) from async_drop", &[])
+                    .await
+            };
+
+            let transaction = conn.r#async().transaction().await?;
+            transaction
+                .r#async()
+                .execute("insert into async_drop values (1)")
+                .await?;
+            drop(transaction);
+            assert_eq!(count().await?, 0);
+
+            let transaction = conn.r#async().transaction().await?;
+            transaction
+                .r#async()
+                .execute("insert into async_drop values (2)")
+                .await?;
+            drop(transaction);
+            assert_eq!(
+                conn.query_one::<i64>("select count(*) from async_drop", &[])?,
+                0
+            );
+            assert!(!conn.transaction().is_in_transaction()?);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn drop_pending() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+
+        smol::block_on(async {
+            let start = std::time::Instant::now();
+
+            let mut future = Box::pin(conn.r#async().execute("select pg_sleep(10)"));
+            assert!(smol::future::poll_once(&mut future).await.is_none());
+            drop(future);
+
+            assert_eq!(conn.query_one::<i32>("select 1", &[])?, 1);
+
+            let mut future = Box::pin(conn.r#async().execute("select pg_sleep(10)"));
+            assert!(smol::future::poll_once(&mut future).await.is_none());
+            drop(future);
+
+            assert_eq!(conn.r#async().query_one::<i32>("select 2", &[]).await?, 2);
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn exclusive() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+
+        smol::block_on(async {
+            let transaction = conn.r#async().transaction().await?;
+
+            let mut query = std::pin::pin!(conn.r#async().execute("select 1"));
+            assert!(smol::future::poll_once(&mut query).await.is_none());
+
+            transaction.commit().await?;
+            query.await?;
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn send() -> crate::Result {
+        fn is_send<T: Send>(_: T) {}
+
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+
+        is_send(conn.r#async().execute("select 1"));
+        is_send(conn.r#async().transaction());
+        // The parameters slice is borrowed across the await.
+        is_send(async {
+            conn.r#async()
+                .query_one::<i32>("select $1::int", &[&1])
+                .await
+        });
+        is_send(async {
+            conn.r#async()
+                .find_where::<Model>("name = $*", &[&"name"], None)
+                .await
+        });
+        // Entities are rendered before the first await.
+        let entity = Entity {
+            id: 1,
+            name: "name".to_string(),
+        };
+        is_send(conn.r#async().insert_one::<Model>(&entity));
+        is_send(conn.r#async().delete_one::<Model>(&entity));
+        // Primary keys are borrowed, their values must be `Sync`.
+        let pk = std::collections::HashMap::from([("id", &1)]);
+        is_send(conn.r#async().find_by_pk::<Model>(&pk));
+        is_send(conn.r#async().update_one::<Model>(&pk, &entity));
+
+        Ok(())
+    }
+
+    #[test]
+    fn poisoned() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        let mutex = conn.connection.clone();
+
+        std::thread::spawn(move || {
+            let _lock = mutex.lock();
+            panic!();
+        })
+        .join()
+        .ok();
+
+        assert!(matches!(
+            smol::block_on(conn.r#async()),
+            Err(crate::Error::Mutex(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn wait() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+
+        smol::block_on(async {
+            let mut polls = 0;
+            let mut query = std::pin::pin!(conn.r#async().execute("select pg_sleep(0.2)"));
+
+            std::future::poll_fn(|ctx| {
+                polls += 1;
+                query.as_mut().poll(ctx)
+            })
+            .await?;
+
+            // The task is only woken when the socket is ready.
+            assert!(polls < 10, "{polls} polls");
+
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn shared_socket() -> crate::Result {
+        use futures_core::Stream as _;
+
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        let transaction = conn.r#async().transaction().await?;
+
+        let mut notifications = transaction.r#async().notifications().await?;
+        notifications.listen("async_shared_socket").await?;
+        // Registers the socket of the stream.
+        assert!(
+            std::future::poll_fn(|ctx| {
+                std::task::Poll::Ready(std::pin::Pin::new(&mut notifications).poll_next(ctx))
+            })
+            .await
+            .is_pending()
+        );
+
+        let one = transaction
+            .r#async()
+            .query_one::<i32>("select 1", &[])
+            .await?;
+        assert_eq!(one, 1);
+
+        drop(notifications);
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+
+        let result = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            conn.r#async()
+                .query_one::<i32>("select 1 from pg_sleep(0.1)", &[]),
+        )
+        .await
+        .unwrap()?;
+
+        assert_eq!(result, 1);
+
+        Ok(())
+    }
+}
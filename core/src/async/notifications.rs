@@ -0,0 +1,99 @@
+/**
+ * Stream of notifications created by the [`Async::notifications`] method.
+ *
+ * The connection is exclusively held by the stream: use its [`listen`] and
+ * [`unlisten`] methods to change the listened channels.
+ *
+ * ```no_run
+ * # fn main() -> elephantry::Result {
+ * # smol::block_on(async {
+ * use smol::stream::StreamExt as _;
+ *
+ * let connection = elephantry::Connection::new("")?;
+ * let mut notifications = connection.r#async().notifications().await?;
+ * notifications.listen("channel").await?;
+ *
+ * while let Some(notify) = notifications.next().await {
+ *     println!("{}", notify?.extra);
+ * }
+ * # Ok(())
+ * # })
+ * # }
+ * ```
+ *
+ * [`Async::notifications`]: crate::Async::notifications
+ * [`listen`]: #method.listen
+ * [`unlisten`]: #method.unlisten
+ */
+#[derive(Debug)]
+pub struct AsyncNotifications<'c> {
+    r#async: super::Async<'c>,
+}
+
+impl<'c> AsyncNotifications<'c> {
+    pub(crate) fn new(r#async: super::Async<'c>) -> Self {
+        Self { r#async }
+    }
+
+    /**
+     * Async version of [`Connection::listen`].
+     *
+     * [`Connection::listen`]: crate::Connection::listen
+     */
+    pub async fn listen(&mut self, channel: &str) -> crate::Result {
+        self.execute(&format!("listen {channel}")).await
+    }
+
+    /**
+     * Async version of [`Connection::unlisten`].
+     *
+     * [`Connection::unlisten`]: crate::Connection::unlisten
+     */
+    pub async fn unlisten(&mut self, channel: &str) -> crate::Result {
+        self.execute(&format!("unlisten {channel}")).await
+    }
+
+    async fn execute(&mut self, query: &str) -> crate::Result {
+        self.r#async
+            .send(query, |connection| connection.send_query(query))
+            .await?;
+
+        // Notifications received meanwhile are kept by libpq.
+        (&mut self.r#async).await.map(|_| ())
+    }
+}
+
+impl futures_core::Stream for AsyncNotifications<'_> {
+    type Item = crate::Result<crate::Notify>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        // Notifications already read by the synchronous connection.
+        if let Some(notify) = this
+            .r#async
+            .connection
+            .pending_notifies
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .pop_front()
+        {
+            return std::task::Poll::Ready(Some(Ok(notify)));
+        }
+
+        let connection = this.r#async.pq()?;
+
+        loop {
+            super::socket::consume_input(&connection)?;
+
+            if let Some(notify) = connection.notifies() {
+                return std::task::Poll::Ready(Some(notify.try_into()));
+            }
+
+            std::task::ready!(this.r#async.socket(&connection)?.poll_read_ready(ctx))?;
+        }
+    }
+}
@@ -0,0 +1,527 @@
+/**
+ * Async connections pool.
+ *
+ * Connections are opened on demand with [`Connection::new_async`], up to
+ * [`PoolOptions::max_size`]. Idle connections are closed after
+ * [`PoolOptions::idle_timeout`].
+ *
+ * With the `tokio` feature, a task of the runtime of the first [`get`] call
+ * closes the expired idle connections in background. With the `async-io`
+ * feature, which has no executor, a thread does it. Otherwise, they are only
+ * closed by [`get`] and [`reap`], and each wait for
+ * [`PoolOptions::acquire_timeout`] spawns a thread to wake the task.
+ *
+ * ```no_run
+ * # fn main() -> elephantry::Result {
+ * # smol::block_on(async {
+ * let pool = elephantry::AsyncPool::new("postgres://localhost");
+ *
+ * let connection = pool.get().await?;
+ * connection.r#async().execute("select 1").await?;
+ * # Ok(())
+ * # })
+ * # }
+ * ```
+ *
+ * [`Connection::new_async`]: crate::Connection::new_async
+ * [`PoolOptions::max_size`]: crate::PoolOptions::max_size
+ * [`PoolOptions::idle_timeout`]: crate::PoolOptions::idle_timeout
+ * [`PoolOptions::acquire_timeout`]: crate::PoolOptions::acquire_timeout
+ * [`get`]: #method.get
+ * [`reap`]: #method.reap
+ */
+#[derive(Clone, Debug)]
+pub struct AsyncPool {
+    inner: std::sync::Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    dsn: String,
+    options: crate::PoolOptions,
+    state: std::sync::Mutex<State>,
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    reaper: std::sync::Once,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    idle: std::collections::VecDeque<Idle>,
+    /**
+     * Number of connections in use, idle or being opened.
+     */
+    size: usize,
+    waiters: Vec<std::task::Waker>,
+}
+
+#[derive(Debug)]
+struct Idle {
+    connection: crate::Connection,
+    created: std::time::Instant,
+    since: std::time::Instant,
+}
+
+enum Slot {
+    Idle(crate::Connection, std::time::Instant),
+    New,
+}
+
+impl AsyncPool {
+    /**
+     * Creates a new pool with the default options. No connection is opened
+     * until the first [`get`] call.
+     *
+     * [`get`]: #method.get
+     */
+    #[must_use]
+    pub fn new(dsn: &str) -> Self {
+        Self::with_options(dsn, crate::PoolOptions::default())
+    }
+
+    /**
+     * Creates a new pool from [`Config`].
+     *
+     * [`Config`]: crate::Config
+     */
+    #[must_use]
+    pub fn from_config(config: &crate::Config, options: crate::PoolOptions) -> Self {
+        Self::with_options(&config.to_string(), options)
+    }
+
+    #[must_use]
+    pub fn with_options(dsn: &str, options: crate::PoolOptions) -> Self {
+        let inner = Inner {
+            dsn: dsn.to_string(),
+            options,
+            state: std::sync::Mutex::default(),
+            #[cfg(any(feature = "tokio", feature = "async-io"))]
+            reaper: std::sync::Once::new(),
+        };
+
+        Self {
+            inner: std::sync::Arc::new(inner),
+        }
+    }
+
+    /**
+     * Retreives a connection, waiting up to [`PoolOptions::acquire_timeout`]
+     * if all connections are in use.
+     *
+     * [`PoolOptions::acquire_timeout`]: crate::PoolOptions::acquire_timeout
+     */
+    pub async fn get(&self) -> crate::Result<AsyncPooled> {
+        #[cfg(any(feature = "tokio", feature = "async-io"))]
+        self.inner.reaper.call_once(|| self.spawn_reaper());
+
+        let mut timeout = self
+            .inner
+            .options
+            .acquire_timeout
+            .map(|x| super::timer::Timer::at(std::time::Instant::now() + x));
+
+        loop {
+            let slot = std::future::poll_fn(|ctx| {
+                if let Some(slot) = self.inner.try_acquire(ctx)? {
+                    return std::task::Poll::Ready(Ok(slot));
+                }
+
+                if let Some(timer) = &mut timeout
+                    && std::future::Future::poll(std::pin::Pin::new(timer), ctx).is_ready()
+                {
+                    return std::task::Poll::Ready(Err(crate::Error::PoolTimeout));
+                }
+
+                std::task::Poll::Pending
+            })
+            .await?;
+
+            // Releases the slot if the connection is unusable or the future
+            // is dropped.
+            let mut pooled = AsyncPooled {
+                inner: self.inner.clone(),
+                connection: None,
+                created: std::time::Instant::now(),
+            };
+
+            match slot {
+                Slot::Idle(connection, created) => {
+                    if self.inner.is_healthy(&connection).await {
+                        pooled.connection = Some(connection);
+                        pooled.created = created;
+                        return Ok(pooled);
+                    }
+                }
+                Slot::New => {
+                    pooled.connection = Some(
+                        crate::Connection::new_async_with_init(
+                            &self.inner.dsn,
+                            self.inner.options.init.clone(),
+                        )
+                        .await?,
+                    );
+                    return Ok(pooled);
+                }
+            }
+        }
+    }
+
+    /**
+     * Closes idle connections unused since [`PoolOptions::idle_timeout`] or
+     * older than [`PoolOptions::max_lifetime`]. This is also done on each
+     * checkout.
+     *
+     * [`PoolOptions::idle_timeout`]: crate::PoolOptions::idle_timeout
+     * [`PoolOptions::max_lifetime`]: crate::PoolOptions::max_lifetime
+     */
+    pub fn reap(&self) -> crate::Result {
+        let mut state = self.inner.state()?;
+        self.inner.reap(&mut state);
+
+        Ok(())
+    }
+
+    /**
+     * Periodically reaps the idle connections until the pool is dropped.
+     */
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    #[allow(clippy::needless_return)]
+    fn spawn_reaper(&self) {
+        let Some(interval) = self.inner.reap_interval() else {
+            return;
+        };
+
+        let inner = std::sync::Arc::downgrade(&self.inner);
+        let reap = move || {
+            let Some(inner) = inner.upgrade() else {
+                return false;
+            };
+
+            if let Ok(mut state) = inner.state() {
+                inner.reap(&mut state);
+            }
+
+            true
+        };
+
+        #[cfg(feature = "tokio")]
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+
+                    if !reap() {
+                        break;
+                    }
+                }
+            });
+
+            return;
+        }
+
+        #[cfg(feature = "async-io")]
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(interval);
+
+                if !reap() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /**
+     * Number of opened connections.
+     */
+    pub fn size(&self) -> crate::Result<usize> {
+        Ok(self.inner.state()?.size)
+    }
+
+    /**
+     * Number of idle connections.
+     */
+    pub fn idle(&self) -> crate::Result<usize> {
+        Ok(self.inner.state()?.idle.len())
+    }
+}
+
+impl Inner {
+    fn state(&self) -> crate::Result<std::sync::MutexGuard<'_, State>> {
+        self.state
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))
+    }
+
+    fn try_acquire(&self, ctx: &mut std::task::Context<'_>) -> crate::Result<Option<Slot>> {
+        let mut state = self.state()?;
+        self.reap(&mut state);
+
+        if let Some(idle) = state.idle.pop_back() {
+            return Ok(Some(Slot::Idle(idle.connection, idle.created)));
+        }
+
+        if state.size < self.options.max_size {
+            state.size += 1;
+            return Ok(Some(Slot::New));
+        }
+
+        if !state.waiters.iter().any(|x| x.will_wake(ctx.waker())) {
+            state.waiters.push(ctx.waker().clone());
+        }
+
+        Ok(None)
+    }
+
+    /**
+     * Delay between two background reaps, `None` if connections never expire.
+     */
+    #[cfg(any(feature = "tokio", feature = "async-io"))]
+    fn reap_interval(&self) -> Option<std::time::Duration> {
+        let interval = match (self.options.idle_timeout, self.options.max_lifetime) {
+            (Some(idle_timeout), Some(max_lifetime)) => idle_timeout.min(max_lifetime),
+            (idle_timeout, max_lifetime) => idle_timeout.or(max_lifetime)?,
+        };
+
+        Some(interval.max(std::time::Duration::from_secs(1)))
+    }
+
+    fn reap(&self, state: &mut State) {
+        let options = &self.options;
+        let min_size = options.min_size;
+        let mut size = state.size;
+
+        state.idle.retain(|x| {
+            let expired = options.is_expired(x.created)
+                || (size > min_size && options.is_idle_expired(x.since));
+
+            if expired {
+                size -= 1;
+            }
+
+            !expired
+        });
+
+        state.size = size;
+    }
+
+    async fn is_healthy(&self, connection: &crate::Connection) -> bool {
+        if connection.has_broken().unwrap_or(true) {
+            return false;
+        }
+
+        !self.options.test_on_checkout || connection.r#async().execute("select 1").await.is_ok()
+    }
+
+    fn release(&self, connection: Option<crate::Connection>, created: std::time::Instant) {
+        let connection = connection.filter(|x| {
+            !x.has_broken().unwrap_or(true)
+                && matches!(x.transaction_status(), Ok(libpq::transaction::Status::Idle))
+                && !self.options.is_expired(created)
+        });
+
+        let Ok(mut state) = self.state() else {
+            return;
+        };
+
+        match connection {
+            Some(connection) => state.idle.push_back(Idle {
+                connection,
+                created,
+                since: std::time::Instant::now(),
+            }),
+            None => state.size -= 1,
+        }
+
+        let waiters = std::mem::take(&mut state.waiters);
+        drop(state);
+
+        for waiter in waiters {
+            waiter.wake();
+        }
+    }
+}
+
+/**
+ * Resets a connection before reusing it: rolls back a leftover transaction,
+ * optionally discards the session state and initializes it again, then
+ * checks the connection is alive.
+ */
+#[cfg(any(feature = "bb8", feature = "deadpool"))]
+pub(crate) async fn reset(connection: &crate::Connection, discard: bool) -> crate::Result {
+    // Rolled back by the next async operation.
+    if connection.transaction_status()? != libpq::transaction::Status::Idle {
+        connection.exclusive.set_dirty();
+    }
+
+    if discard {
+        connection.r#async().execute("discard all").await?;
+        connection.init.run_async(connection).await?;
+    }
+
+    connection.r#async().execute("select 1").await.map(|_| ())
+}
+
+/**
+ * A connection checked out from [`AsyncPool`], returned to the pool when
+ * dropped.
+ *
+ * Broken connections, connections left in a transaction or older than
+ * [`PoolOptions::max_lifetime`] are closed instead.
+ *
+ * [`PoolOptions::max_lifetime`]: crate::PoolOptions::max_lifetime
+ */
+#[derive(Debug)]
+pub struct AsyncPooled {
+    inner: std::sync::Arc<Inner>,
+    connection: Option<crate::Connection>,
+    created: std::time::Instant,
+}
+
+impl std::ops::Deref for AsyncPooled {
+    type Target = crate::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl Drop for AsyncPooled {
+    fn drop(&mut self) {
+        self.inner.release(self.connection.take(), self.created);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    fn options() -> crate::PoolOptions {
+        crate::PoolOptions {
+            max_size: 2,
+            acquire_timeout: Some(std::time::Duration::from_millis(100)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn connect() {
+        smol::block_on(async {
+            let connection = crate::Connection::new_async(&crate::test::dsn()).await;
+            assert!(connection.is_ok());
+
+            let connection = crate::Connection::new_async("host=/invalid").await;
+            assert!(matches!(connection, Err(crate::Error::Connect { .. })));
+        });
+    }
+
+    #[test]
+    fn checkout() -> crate::Result {
+        let pool = crate::AsyncPool::with_options(&crate::test::dsn(), options());
+
+        smol::block_on(async {
+            let first = pool.get().await?;
+            let second = pool.get().await?;
+            assert_eq!(pool.size()?, 2);
+
+            assert!(matches!(pool.get().await, Err(crate::Error::PoolTimeout)));
+
+            let pid = first.query_one::<i32>("select pg_backend_pid()", &[])?;
+            drop(first);
+            assert_eq!(pool.idle()?, 1);
+
+            let third = pool.get().await?;
+            assert_eq!(third.query_one::<i32>("select pg_backend_pid()", &[])?, pid);
+
+            // Broken connection
+            second.query_one::<bool>("select pg_terminate_backend($*)", &[&pid])?;
+            drop(third);
+            assert_eq!(pool.idle()?, 1);
+
+            let fourth = pool.get().await?;
+            assert_ne!(
+                fourth.query_one::<i32>("select pg_backend_pid()", &[])?,
+                pid
+            );
+            assert_eq!(pool.size()?, 2);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn wait() -> crate::Result {
+        let pool = crate::AsyncPool::with_options(&crate::test::dsn(), options());
+
+        smol::block_on(async {
+            let first = pool.get().await?;
+            let _second = pool.get().await?;
+
+            let release = async {
+                smol::Timer::after(std::time::Duration::from_millis(20)).await;
+                drop(first);
+            };
+            let (_, third) = smol::future::zip(release, pool.get()).await;
+            assert!(third.is_ok());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn reap() -> crate::Result {
+        let pool = crate::AsyncPool::with_options(
+            &crate::test::dsn(),
+            crate::PoolOptions {
+                idle_timeout: Some(std::time::Duration::ZERO),
+                ..options()
+            },
+        );
+
+        smol::block_on(async {
+            drop(pool.get().await?);
+            assert_eq!(pool.size()?, 1);
+
+            pool.reap()?;
+            assert_eq!(pool.size()?, 0);
+
+            Ok(())
+        })
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn background_reap() -> crate::Result {
+        let pool = crate::AsyncPool::with_options(
+            &crate::test::dsn(),
+            crate::PoolOptions {
+                idle_timeout: Some(std::time::Duration::from_secs(1)),
+                ..options()
+            },
+        );
+
+        drop(pool.get().await?);
+        assert_eq!(pool.idle()?, 1);
+
+        tokio::time::sleep(std::time::Duration::from_millis(2_500)).await;
+        assert_eq!(pool.size()?, 0);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio() -> crate::Result {
+        let pool = crate::AsyncPool::with_options(&crate::test::dsn(), options());
+
+        let connection = pool.get().await?;
+        let _second = pool.get().await?;
+        assert_eq!(
+            connection
+                .r#async()
+                .query_one::<i32>("select 1", &[])
+                .await?,
+            1
+        );
+        assert!(matches!(pool.get().await, Err(crate::Error::PoolTimeout)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+/**
+ * Stream of entities created by the [`Async::query_stream`] method.
+ *
+ * Rows are retreived one by one in single-row mode: the next row is read
+ * from the connection only when the stream is polled. Dropping the stream
+ * before its end cancels the query.
+ *
+ * [`Async::query_stream`]: crate::Async::query_stream
+ */
+#[derive(Debug)]
+pub struct AsyncRows<'c, E: crate::Entity> {
+    r#async: super::Async<'c>,
+    marker: std::marker::PhantomData<fn() -> E>,
+}
+
+impl<'c, E: crate::Entity> AsyncRows<'c, E> {
+    pub(crate) fn new(r#async: super::Async<'c>) -> Self {
+        Self {
+            r#async,
+            marker: std::marker::PhantomData,
+        }
+    }
+
+    fn poll_row(
+        &mut self,
+        connection: &libpq::Connection,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<crate::Result<E>>> {
+        use std::task::Poll;
+
+        std::task::ready!(self.r#async.poll_flush(connection, ctx))?;
+
+        loop {
+            super::socket::consume_input(connection)?;
+
+            while !connection.is_busy() {
+                let Some(result) = connection.result() else {
+                    self.r#async.pending = false;
+
+                    return Poll::Ready(None);
+                };
+
+                match result.status() {
+                    libpq::Status::SingleTuple => {
+                        let tuple = crate::Tuple::from(&result, 0);
+
+                        return Poll::Ready(Some(Ok(E::from(&tuple))));
+                    }
+                    // The remaining results are consumed by the next poll.
+                    _ => {
+                        if let Err(err) = crate::pq::Result::try_from(result) {
+                            return Poll::Ready(Some(Err(err.or_lost(connection))));
+                        }
+                    }
+                }
+            }
+
+            std::task::ready!(self.r#async.socket(connection)?.poll_read_ready(ctx))?;
+        }
+    }
+}
+
+impl<E: crate::Entity> futures_core::Stream for AsyncRows<'_, E> {
+    type Item = crate::Result<E>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if !this.r#async.pending {
+            return std::task::Poll::Ready(None);
+        }
+
+        let connection = match this.r#async.pq() {
+            Ok(connection) => connection,
+            Err(err) => return std::task::Poll::Ready(Some(Err(err))),
+        };
+
+        let poll = this.poll_row(&connection, ctx);
+
+        // Only SQL errors leave the connection in a known state.
+        if let std::task::Poll::Ready(Some(Err(err))) = &poll
+            && !matches!(err, crate::Error::Sql(_))
+        {
+            this.r#async.pending = false;
+        }
+
+        poll
+    }
+}
@@ -0,0 +1,522 @@
+/**
+ * Borrowed libpq socket descriptor: libpq stays the owner of the file
+ * descriptor, it must not be closed when the registration is dropped.
+ */
+#[cfg(all(unix, any(feature = "tokio", feature = "async-io")))]
+#[derive(Debug)]
+pub(crate) struct Fd(std::os::unix::io::RawFd);
+
+#[cfg(all(unix, feature = "tokio"))]
+impl std::os::unix::io::AsRawFd for Fd {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.0
+    }
+}
+
+#[cfg(all(unix, feature = "async-io"))]
+impl std::os::unix::io::AsFd for Fd {
+    fn as_fd(&self) -> std::os::unix::io::BorrowedFd<'_> {
+        // SAFETY: the descriptor stays open as long as the libpq connection,
+        // which outlives the registration.
+        unsafe { std::os::unix::io::BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+/**
+ * libpq socket registered in the reactor of the current runtime.
+ *
+ * Without the `tokio` or `async-io` feature, there is no reactor to register
+ * into: a helper thread waits for the sockets readiness, see [`Pollers`].
+ */
+#[derive(Debug)]
+pub(crate) enum Socket {
+    #[cfg(all(unix, feature = "tokio"))]
+    Tokio(tokio::io::unix::AsyncFd<Fd>),
+    #[cfg(all(unix, feature = "async-io"))]
+    AsyncIo(async_io::Async<Fd>),
+    Thread(Poller),
+}
+
+impl Socket {
+    pub fn new(connection: &libpq::Connection) -> crate::Result<Self> {
+        #[cfg(all(unix, feature = "tokio"))]
+        if tokio::runtime::Handle::try_current().is_ok() {
+            let fd = Fd(connection.socket()?);
+
+            return Ok(Self::Tokio(tokio::io::unix::AsyncFd::new(fd)?));
+        }
+
+        #[cfg(all(unix, feature = "async-io"))]
+        {
+            let fd = Fd(connection.socket()?);
+
+            return Ok(Self::AsyncIo(async_io::Async::new_nonblocking(fd)?));
+        }
+
+        #[allow(unreachable_code)]
+        Ok(Self::Thread(Poller::new(connection.socket()?)?))
+    }
+
+    pub fn poll_read_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<crate::Result> {
+        match self {
+            #[cfg(all(unix, feature = "tokio"))]
+            Self::Tokio(fd) => Self::tokio(fd, libc::POLLIN, |ctx| fd.poll_read_ready(ctx), ctx),
+            #[cfg(all(unix, feature = "async-io"))]
+            Self::AsyncIo(fd) => fd.poll_readable(ctx).map_err(Into::into),
+            Self::Thread(poller) => poller.poll_ready(Interest::Read, ctx),
+        }
+    }
+
+    pub fn poll_write_ready(
+        &self,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<crate::Result> {
+        match self {
+            #[cfg(all(unix, feature = "tokio"))]
+            Self::Tokio(fd) => Self::tokio(fd, libc::POLLOUT, |ctx| fd.poll_write_ready(ctx), ctx),
+            #[cfg(all(unix, feature = "async-io"))]
+            Self::AsyncIo(fd) => fd.poll_writable(ctx).map_err(Into::into),
+            Self::Thread(poller) => poller.poll_ready(Interest::Write, ctx),
+        }
+    }
+
+    /**
+     * Tokio readiness is edge-triggered: the readiness is only cleared if the
+     * socket isn't actually ready, otherwise the next event could never come.
+     */
+    #[cfg(all(unix, feature = "tokio"))]
+    fn tokio<'a>(
+        fd: &'a tokio::io::unix::AsyncFd<Fd>,
+        events: libc::c_short,
+        poll: impl Fn(
+            &mut std::task::Context<'_>,
+        )
+            -> std::task::Poll<std::io::Result<tokio::io::unix::AsyncFdReadyGuard<'a, Fd>>>,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<crate::Result> {
+        loop {
+            let mut guard = std::task::ready!(poll(ctx))?;
+
+            let mut pollfd = libc::pollfd {
+                fd: fd.get_ref().0,
+                events,
+                revents: 0,
+            };
+
+            if unsafe { libc::poll(&mut pollfd, 1, 0) } != 0 {
+                return std::task::Poll::Ready(Ok(()));
+            }
+
+            guard.clear_ready();
+        }
+    }
+}
+
+/**
+ * Registration of the socket shared by the async handles of a connection: a
+ * file descriptor can only be registered once in a reactor (`EEXIST`), but
+ * several handles can be alive at once, for example a notifications stream
+ * and a query in the same transaction.
+ */
+#[derive(Debug, Default)]
+pub(crate) struct Registration {
+    socket: std::sync::Mutex<Option<(i32, std::sync::Weak<Socket>)>>,
+}
+
+impl Registration {
+    pub fn socket(&self, connection: &libpq::Connection) -> crate::Result<std::sync::Arc<Socket>> {
+        let mut registered = self
+            .socket
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+        let fd = connection.socket()?;
+
+        if let Some((registered_fd, socket)) = registered.as_ref()
+            && *registered_fd == fd
+            && let Some(socket) = socket.upgrade()
+        {
+            return Ok(socket);
+        }
+
+        let socket = std::sync::Arc::new(Socket::new(connection)?);
+        *registered = Some((fd, std::sync::Arc::downgrade(&socket)));
+
+        Ok(socket)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Interest {
+    Read,
+    Write,
+}
+
+/**
+ * Waits for the socket readiness in the helper thread shared by all the
+ * pollers, see [`Pollers`].
+ */
+#[derive(Debug)]
+pub(crate) struct Poller {
+    fd: i32,
+    state: std::sync::Arc<std::sync::Mutex<PollerState>>,
+}
+
+#[derive(Debug, Default)]
+struct PollerState {
+    waker: Option<std::task::Waker>,
+    /**
+     * The readiness waited by the helper thread, if any.
+     */
+    interest: Option<Interest>,
+}
+
+impl Poller {
+    fn new(fd: i32) -> crate::Result<Self> {
+        let poller = Self {
+            fd,
+            state: std::sync::Arc::default(),
+        };
+
+        Pollers::get()?.register(fd, &poller.state)?;
+
+        Ok(poller)
+    }
+
+    fn poll_ready(
+        &self,
+        interest: Interest,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<crate::Result> {
+        if wait(self.fd, interest, std::time::Duration::ZERO)? {
+            return std::task::Poll::Ready(Ok(()));
+        }
+
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        state.waker = Some(ctx.waker().clone());
+
+        if state.interest != Some(interest) {
+            state.interest = Some(interest);
+            drop(state);
+
+            Pollers::get()?.notify();
+        }
+
+        std::task::Poll::Pending
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        if let Ok(pollers) = Pollers::get() {
+            pollers.unregister(&self.state);
+        }
+    }
+}
+
+type Waiting = Vec<(i32, Interest, std::sync::Arc<std::sync::Mutex<PollerState>>)>;
+
+/**
+ * Helper thread waiting for the sockets readiness of every [`Poller`].
+ *
+ * The thread is woken up through a pipe to take new waits into account, or to
+ * forget the socket of a dropped poller: its descriptor may be closed
+ * meanwhile.
+ */
+#[derive(Debug)]
+struct Pollers {
+    pollers: std::sync::Mutex<Vec<(i32, std::sync::Arc<std::sync::Mutex<PollerState>>)>>,
+    #[cfg(unix)]
+    pipe: [i32; 2],
+}
+
+impl Pollers {
+    fn get() -> crate::Result<&'static Self> {
+        static POLLERS: std::sync::OnceLock<std::io::Result<Pollers>> = std::sync::OnceLock::new();
+        static THREAD: std::sync::Once = std::sync::Once::new();
+
+        let pollers = POLLERS
+            .get_or_init(Self::new)
+            .as_ref()
+            .map_err(|e| std::io::Error::new(e.kind(), e.to_string()))?;
+
+        THREAD.call_once(|| {
+            std::thread::spawn(|| pollers.run());
+        });
+
+        Ok(pollers)
+    }
+
+    #[cfg(unix)]
+    fn new() -> std::io::Result<Self> {
+        let mut pipe = [0; 2];
+
+        if unsafe { libc::pipe(pipe.as_mut_ptr()) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        for fd in pipe {
+            unsafe {
+                libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK);
+                libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            }
+        }
+
+        Ok(Self {
+            pollers: std::sync::Mutex::default(),
+            pipe,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            pollers: std::sync::Mutex::default(),
+        })
+    }
+
+    fn register(
+        &self,
+        fd: i32,
+        state: &std::sync::Arc<std::sync::Mutex<PollerState>>,
+    ) -> crate::Result {
+        self.pollers
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .push((fd, state.clone()));
+
+        Ok(())
+    }
+
+    fn unregister(&self, state: &std::sync::Arc<std::sync::Mutex<PollerState>>) {
+        if let Ok(mut pollers) = self.pollers.lock() {
+            pollers.retain(|(_, x)| !std::sync::Arc::ptr_eq(x, state));
+        }
+
+        self.notify();
+    }
+
+    #[cfg(unix)]
+    fn notify(&self) {
+        // The pipe is full if the thread is already notified.
+        unsafe { libc::write(self.pipe[1], [0u8].as_ptr().cast(), 1) };
+    }
+
+    #[cfg(not(unix))]
+    fn notify(&self) {}
+
+    fn run(&self) {
+        loop {
+            let waiting = match self.pollers.lock() {
+                Ok(pollers) => pollers
+                    .iter()
+                    .filter_map(|(fd, state)| {
+                        let interest = state.lock().ok()?.interest?;
+
+                        Some((*fd, interest, state.clone()))
+                    })
+                    .collect::<Waiting>(),
+                Err(_) => return,
+            };
+
+            let ready = self.wait(&waiting);
+
+            for ((_, _, state), ready) in waiting.iter().zip(ready) {
+                if ready && let Ok(mut state) = state.lock() {
+                    state.interest = None;
+
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * Waits for the readiness of at least one socket, or a notification.
+     */
+    #[cfg(unix)]
+    fn wait(&self, waiting: &Waiting) -> Vec<bool> {
+        let mut fds = vec![libc::pollfd {
+            fd: self.pipe[0],
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+
+        fds.extend(waiting.iter().map(|(fd, interest, _)| libc::pollfd {
+            fd: *fd,
+            events: events(*interest),
+            revents: 0,
+        }));
+
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } == -1 {
+            // The pollers check the readiness again.
+            return vec![true; waiting.len()];
+        }
+
+        if fds[0].revents != 0 {
+            let mut buf = [0u8; 64];
+
+            while unsafe { libc::read(self.pipe[0], buf.as_mut_ptr().cast(), buf.len()) } > 0 {}
+        }
+
+        fds[1..].iter().map(|x| x.revents != 0).collect()
+    }
+
+    /**
+     * Without `poll`, the sockets are considered ready after a short sleep.
+     */
+    #[cfg(not(unix))]
+    fn wait(&self, waiting: &Waiting) -> Vec<bool> {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        vec![true; waiting.len()]
+    }
+}
+
+#[cfg(unix)]
+fn events(interest: Interest) -> libc::c_short {
+    match interest {
+        Interest::Read => libc::POLLIN,
+        Interest::Write => libc::POLLOUT,
+    }
+}
+
+/**
+ * Waits at most `timeout` for the socket readiness.
+ */
+#[cfg(unix)]
+fn wait(fd: i32, interest: Interest, timeout: std::time::Duration) -> crate::Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: events(interest),
+        revents: 0,
+    };
+    let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+
+    match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+        -1 => {
+            let err = std::io::Error::last_os_error();
+
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                Ok(false)
+            } else {
+                Err(err.into())
+            }
+        }
+        0 => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+/**
+ * Without `poll`, the socket is never ready immediately, see [`Pollers`].
+ */
+#[cfg(not(unix))]
+fn wait(_: i32, _: Interest, _: std::time::Duration) -> crate::Result<bool> {
+    Ok(false)
+}
+
+/**
+ * Queues COPY data, returns `false` if the output buffer is full.
+ */
+pub(crate) fn put_copy_data(connection: &libpq::Connection, buffer: &[u8]) -> crate::Result<bool> {
+    let status = unsafe {
+        libpq_sys::PQputCopyData(
+            connection.into(),
+            buffer.as_ptr().cast(),
+            buffer.len().try_into()?,
+        )
+    };
+
+    copy_status(connection, status)
+}
+
+/**
+ * Queues the end of COPY data, returns `false` if the output buffer is full.
+ * The COPY fails if an `error` message is given.
+ */
+pub(crate) fn put_copy_end(
+    connection: &libpq::Connection,
+    error: Option<&std::ffi::CStr>,
+) -> crate::Result<bool> {
+    let error = error.map_or(std::ptr::null(), std::ffi::CStr::as_ptr);
+    let status = unsafe { libpq_sys::PQputCopyEnd(connection.into(), error) };
+
+    copy_status(connection, status)
+}
+
+fn copy_status(connection: &libpq::Connection, status: i32) -> crate::Result<bool> {
+    match status {
+        1 => Ok(true),
+        0 => Ok(false),
+        _ => Err(crate::Error::Copy(error(connection))),
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum CopyData {
+    Row(Vec<u8>),
+    /**
+     * No row is available yet, more input must be consumed.
+     */
+    Pending,
+    Done,
+}
+
+/**
+ * Retreives a COPY row without blocking.
+ */
+pub(crate) fn get_copy_data(connection: &libpq::Connection) -> crate::Result<CopyData> {
+    let mut ptr = std::ptr::null_mut();
+
+    let len = unsafe { libpq_sys::PQgetCopyData(connection.into(), &mut ptr, 1) };
+
+    match len {
+        -2 => Err(crate::Error::Copy(error(connection)).or_lost(connection)),
+        -1 => Ok(CopyData::Done),
+        0 => Ok(CopyData::Pending),
+        len => {
+            let row =
+                unsafe { std::slice::from_raw_parts(ptr.cast::<u8>(), len as usize) }.to_vec();
+            unsafe { libpq_sys::PQfreemem(ptr.cast()) };
+
+            Ok(CopyData::Row(row))
+        }
+    }
+}
+
+/**
+ * Flushes queued output data, returns `false` if some data are still waiting
+ * for the socket to be writable.
+ */
+pub(crate) fn flush(connection: &libpq::Connection) -> crate::Result<bool> {
+    match unsafe { libpq_sys::PQflush(connection.into()) } {
+        0 => Ok(true),
+        1 => Ok(false),
+        _ => Err(crate::Error::Async(error(connection)).or_lost(connection)),
+    }
+}
+
+/**
+ * Reads the data available on the socket.
+ */
+pub(crate) fn consume_input(connection: &libpq::Connection) -> crate::Result {
+    connection
+        .consume_input()
+        .map_err(|e| crate::Error::Async(e).or_lost(connection))
+}
+
+pub(crate) fn error(connection: &libpq::Connection) -> libpq::errors::Error {
+    connection
+        .error_message()
+        .map(|x| libpq::errors::Error::Backend(x.to_string()))
+        .unwrap_or(libpq::errors::Error::Unknow)
+}
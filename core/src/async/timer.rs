@@ -0,0 +1,85 @@
+/**
+ * Timer using the reactor of the current runtime.
+ *
+ * Without the `tokio` or `async-io` feature, a thread is spawned to wake the
+ * task at the deadline.
+ */
+#[derive(Debug)]
+pub(crate) enum Timer {
+    #[cfg(feature = "tokio")]
+    Tokio(std::pin::Pin<Box<tokio::time::Sleep>>),
+    #[cfg(feature = "async-io")]
+    AsyncIo(async_io::Timer),
+    Thread {
+        deadline: std::time::Instant,
+        waker: Option<std::sync::Arc<std::sync::Mutex<std::task::Waker>>>,
+    },
+}
+
+impl Timer {
+    pub fn at(deadline: std::time::Instant) -> Self {
+        #[cfg(feature = "tokio")]
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Self::Tokio(Box::pin(tokio::time::sleep_until(deadline.into())));
+        }
+
+        #[cfg(feature = "async-io")]
+        {
+            return Self::AsyncIo(async_io::Timer::at(deadline));
+        }
+
+        #[allow(unreachable_code)]
+        Self::Thread {
+            deadline,
+            waker: None,
+        }
+    }
+}
+
+impl std::future::Future for Timer {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        ctx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        match &mut *self {
+            #[cfg(feature = "tokio")]
+            Self::Tokio(sleep) => sleep.as_mut().poll(ctx),
+            #[cfg(feature = "async-io")]
+            Self::AsyncIo(timer) => std::pin::Pin::new(timer).poll(ctx).map(|_| ()),
+            Self::Thread { deadline, waker } => {
+                let deadline = *deadline;
+
+                if std::time::Instant::now() >= deadline {
+                    return std::task::Poll::Ready(());
+                }
+
+                match waker {
+                    Some(waker) => {
+                        if let Ok(mut waker) = waker.lock() {
+                            waker.clone_from(ctx.waker());
+                        }
+                    }
+                    None => {
+                        let shared =
+                            std::sync::Arc::new(std::sync::Mutex::new(ctx.waker().clone()));
+                        *waker = Some(shared.clone());
+
+                        std::thread::spawn(move || {
+                            std::thread::sleep(
+                                deadline.saturating_duration_since(std::time::Instant::now()),
+                            );
+
+                            if let Ok(waker) = shared.lock() {
+                                waker.wake_by_ref();
+                            }
+                        });
+                    }
+                }
+
+                std::task::Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+/**
+ * Async version of [`Transaction`].
+ *
+ * This `struct` is created by the [`Async::transaction`] method. The
+ * connection is exclusively held by the transaction: other async operations
+ * on it wait for the transaction end. If the transaction is dropped before
+ * being committed, it is rolled back by the next operation on the
+ * connection, or when the connection is returned to a pool.
+ *
+ * [`Transaction`]: crate::Transaction
+ * [`Async::transaction`]: crate::Async::transaction
+ */
+#[derive(Debug)]
+pub struct AsyncTransaction<'c> {
+    connection: &'c crate::Connection,
+    done: bool,
+}
+
+impl<'c> AsyncTransaction<'c> {
+    pub(crate) fn new(connection: &'c crate::Connection) -> Self {
+        Self {
+            connection,
+            done: false,
+        }
+    }
+
+    /**
+     * Runs async queries in this transaction.
+     */
+    #[must_use]
+    pub fn r#async(&self) -> crate::Async<'_> {
+        crate::Async::for_transaction(self.connection)
+    }
+
+    /**
+     * Commit the transaction.
+     */
+    pub async fn commit(mut self) -> crate::Result {
+        self.exec("commit transaction").await?;
+        self.done = true;
+
+        Ok(())
+    }
+
+    /**
+     * Rollback the whole transaction.
+     */
+    pub async fn rollback(mut self) -> crate::Result {
+        self.exec(&crate::transaction::rollback_query(None)).await?;
+        self.done = true;
+
+        Ok(())
+    }
+
+    /**
+     * Rollback the transaction to the given savepoint.
+     */
+    pub async fn rollback_to(&self, name: &str) -> crate::Result {
+        self.exec(&crate::transaction::rollback_query(Some(name)))
+            .await
+    }
+
+    /**
+     * Set a savepoint in a transaction.
+     */
+    pub async fn set_save_point(&self, name: &str) -> crate::Result {
+        self.exec(&crate::transaction::savepoint_query(name)).await
+    }
+
+    /**
+     * Drop a savepoint.
+     */
+    pub async fn release_savepoint(&self, name: &str) -> crate::Result {
+        self.exec(&crate::transaction::release_savepoint_query(name))
+            .await
+    }
+
+    /**
+     * See [`Transaction::is_transaction_ok`].
+     *
+     * [`Transaction::is_transaction_ok`]: crate::Transaction::is_transaction_ok
+     */
+    pub fn is_transaction_ok(&self) -> crate::Result<Option<bool>> {
+        crate::transaction::is_transaction_ok(self.connection)
+    }
+
+    /**
+     * See [`Transaction::set_deferrable`].
+     *
+     * [`Transaction::set_deferrable`]: crate::Transaction::set_deferrable
+     */
+    pub async fn set_deferrable(
+        &self,
+        keys: Option<Vec<&str>>,
+        constraints: crate::transaction::Constraints,
+    ) -> crate::Result {
+        let query = crate::transaction::deferrable_query(self.connection, keys, constraints)?;
+
+        self.exec(&query).await
+    }
+
+    /**
+     * See [`Transaction::set_isolation_level`].
+     *
+     * [`Transaction::set_isolation_level`]: crate::Transaction::set_isolation_level
+     */
+    pub async fn set_isolation_level(
+        &self,
+        level: crate::transaction::IsolationLevel,
+    ) -> crate::Result {
+        self.exec(&crate::transaction::isolation_level_query(level))
+            .await
+    }
+
+    /**
+     * See [`Transaction::set_access_mode`].
+     *
+     * [`Transaction::set_access_mode`]: crate::Transaction::set_access_mode
+     */
+    pub async fn set_access_mode(&self, mode: crate::transaction::AccessMode) -> crate::Result {
+        self.exec(&crate::transaction::access_mode_query(mode))
+            .await
+    }
+
+    async fn exec(&self, query: &str) -> crate::Result {
+        self.r#async().execute(query).await.map(|_| ())
+    }
+}
+
+impl Drop for AsyncTransaction<'_> {
+    fn drop(&mut self) {
+        // Rolling back here would block: the next user of the connection
+        // does it.
+        if !self.done {
+            self.connection.exclusive.set_dirty();
+        }
+
+        self.connection.exclusive.unlock();
+    }
+}
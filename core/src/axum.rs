@@ -0,0 +1,265 @@
+/*!
+ * [axum](https://crates.io/crates/axum) integration.
+ *
+ * ```no_run
+ * # async fn run() -> elephantry::Result {
+ * async fn count(
+ *     connection: elephantry::axum::Connection,
+ * ) -> elephantry::Result<String> {
+ *     let count = connection
+ *         .r#async()
+ *         .query_one::<i64>("select count(*) from event", &[])
+ *         .await?;
+ *
+ *     Ok(count.to_string())
+ * }
+ *
+ * async fn delete(transaction: elephantry::axum::Transaction) -> elephantry::Result {
+ *     transaction.r#async().execute("delete from event").await?;
+ *     transaction.r#async().execute("delete from log").await?;
+ *
+ *     Ok(())
+ * }
+ *
+ * let pool = elephantry::AsyncPool::new("postgres://localhost");
+ *
+ * let app: axum::Router = axum::Router::new()
+ *     .route("/", axum::routing::get(count).delete(delete))
+ *     .layer(axum::middleware::from_fn(elephantry::axum::transaction))
+ *     .with_state(pool);
+ * # Ok(())
+ * # }
+ * ```
+ */
+
+type Slot = std::sync::Arc<std::sync::Mutex<Option<crate::AsyncPooled>>>;
+
+/**
+ * Extracts a connection from the [`AsyncPool`] of the router state.
+ *
+ * [`AsyncPool`]: crate::AsyncPool
+ */
+#[derive(Debug)]
+pub struct Connection(pub crate::AsyncPooled);
+
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for Connection
+where
+    crate::AsyncPool: axum::extract::FromRef<S>,
+{
+    type Rejection = crate::Error;
+
+    async fn from_request_parts(
+        _: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> crate::Result<Self> {
+        let pool = <crate::AsyncPool as axum::extract::FromRef<S>>::from_ref(state);
+
+        Ok(Self(pool.get().await?))
+    }
+}
+
+impl std::ops::Deref for Connection {
+    type Target = crate::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/**
+ * Extracts a connection in a transaction, committed by the [`transaction`]
+ * middleware if the response is successful and rolled back otherwise.
+ *
+ * [`transaction`]: fn.transaction.html
+ */
+#[derive(Debug)]
+pub struct Transaction {
+    connection: Option<crate::AsyncPooled>,
+    slot: Slot,
+}
+
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for Transaction
+where
+    crate::AsyncPool: axum::extract::FromRef<S>,
+{
+    type Rejection = axum::response::Response;
+
+    async fn from_request_parts(
+        parts: &mut axum::http::request::Parts,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        use axum::response::IntoResponse as _;
+
+        let Some(slot) = parts.extensions.get::<Slot>().cloned() else {
+            log::error!("The Transaction extractor requires the transaction middleware");
+
+            return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        };
+
+        let Connection(connection) = Connection::from_request_parts(parts, state)
+            .await
+            .map_err(|err| err.into_response())?;
+
+        connection
+            .r#async()
+            .execute("begin transaction")
+            .await
+            .map_err(|err| err.into_response())?;
+
+        Ok(Self {
+            connection: Some(connection),
+            slot,
+        })
+    }
+}
+
+impl std::ops::Deref for Transaction {
+    type Target = crate::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        // Gives the connection back to the middleware.
+        if let Ok(mut slot) = self.slot.lock() {
+            *slot = self.connection.take();
+        }
+    }
+}
+
+/**
+ * Middleware ending the transactions opened by the [`Transaction`]
+ * extractor, to use with [`axum::middleware::from_fn`].
+ */
+pub async fn transaction(
+    mut request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::response::IntoResponse as _;
+
+    let slot = Slot::default();
+    request.extensions_mut().insert(slot.clone());
+
+    let response = next.run(request).await;
+
+    let connection = match slot.lock() {
+        Ok(mut slot) => slot.take(),
+        Err(err) => return crate::Error::Mutex(err.to_string()).into_response(),
+    };
+
+    let Some(connection) = connection else {
+        return response;
+    };
+
+    let query = if response.status().is_client_error() || response.status().is_server_error() {
+        crate::transaction::rollback_query(None)
+    } else {
+        "commit transaction".to_string()
+    };
+
+    match connection.r#async().execute(&query).await {
+        Ok(_) => response,
+        Err(err) => err.into_response(),
+    }
+}
+
+impl axum::response::IntoResponse for crate::Error {
+    fn into_response(self) -> axum::response::Response {
+        let status = axum::http::StatusCode::from_u16(self.status_code())
+            .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        if status.is_server_error() {
+            log::error!("{self}");
+        } else {
+            log::debug!("{self}");
+        }
+
+        status.into_response()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    async fn insert(
+        transaction: crate::axum::Transaction,
+        axum::extract::Path(id): axum::extract::Path<i32>,
+    ) -> crate::Result {
+        transaction
+            .r#async()
+            .query_one::<i32>("insert into axum_test values ($1) returning id", &[&id])
+            .await?;
+
+        Ok(())
+    }
+
+    async fn fail(transaction: crate::axum::Transaction) -> crate::Result {
+        transaction
+            .r#async()
+            .execute("insert into axum_test values (100)")
+            .await?;
+
+        Err(crate::Error::NotFound)
+    }
+
+    async fn count(connection: crate::axum::Connection) -> crate::Result<String> {
+        let count = connection
+            .r#async()
+            .query_one::<i64>("select count(*) from axum_test", &[])
+            .await?;
+
+        Ok(count.to_string())
+    }
+
+    async fn call(app: &axum::Router, method: &str, uri: &str) -> axum::http::StatusCode {
+        use tower::ServiceExt as _;
+
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(axum::body::Body::empty())
+            .unwrap();
+
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn axum() -> crate::Result {
+        // A single connection keeps the temporary table.
+        let pool = crate::AsyncPool::with_options(
+            &crate::test::dsn(),
+            crate::PoolOptions {
+                max_size: 1,
+                ..Default::default()
+            },
+        );
+        pool.get()
+            .await?
+            .r#async()
+            .execute("create temporary table axum_test (id int primary key)")
+            .await?;
+
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(count).delete(fail))
+            .route("/{id}", axum::routing::post(insert))
+            .layer(axum::middleware::from_fn(crate::axum::transaction))
+            .with_state(pool.clone());
+
+        assert_eq!(call(&app, "POST", "/1").await, 200);
+        assert_eq!(call(&app, "POST", "/1").await, 409);
+        assert_eq!(call(&app, "DELETE", "/").await, 404);
+        assert_eq!(call(&app, "GET", "/").await, 200);
+
+        let count = pool
+            .get()
+            .await?
+            .r#async()
+            .query_one::<i64>("select count(*) from axum_test", &[])
+            .await?;
+        assert_eq!(count, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+/**
+ * [bb8](https://crates.io/crates/bb8) manager.
+ *
+ * bb8 has no hook when a connection is returned: leftover transactions are
+ * rolled back when the connection is validated on checkout (see
+ * `bb8::Builder::test_on_check_out`).
+ *
+ * ```no_run
+ * # #[tokio::main(flavor = "current_thread")]
+ * # async fn main() -> elephantry::Result {
+ * let manager = elephantry::bb8::ConnectionManager::new("postgres://localhost");
+ * let pool = bb8::Pool::builder().build(manager).await?;
+ *
+ * let connection = pool.get().await.unwrap();
+ * connection.r#async().execute("select 1").await?;
+ * # Ok(())
+ * # }
+ * ```
+ */
+#[derive(Clone, Debug)]
+pub struct ConnectionManager {
+    dsn: String,
+    discard: bool,
+    init: crate::connection::Init,
+}
+
+impl ConnectionManager {
+    #[must_use]
+    pub fn new(dsn: &str) -> Self {
+        Self {
+            dsn: dsn.to_string(),
+            discard: false,
+            init: crate::connection::Init::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn from_config(config: &crate::Config) -> Self {
+        Self::new(&config.to_string())
+    }
+
+    /**
+     * Resets the session state with `DISCARD ALL` when a connection is
+     * validated.
+     */
+    #[must_use]
+    pub fn discard_all(mut self, discard: bool) -> Self {
+        self.discard = discard;
+        self
+    }
+
+    /**
+     * Initialization of each new connection, run again after `DISCARD ALL`.
+     */
+    #[must_use]
+    pub fn init(mut self, init: crate::connection::Init) -> Self {
+        self.init = init;
+        self
+    }
+}
+
+impl bb8::ManageConnection for ConnectionManager {
+    type Connection = crate::Connection;
+    type Error = crate::Error;
+
+    async fn connect(&self) -> crate::Result<Self::Connection> {
+        crate::Connection::new_async_with_init(&self.dsn, self.init.clone()).await
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> crate::Result {
+        crate::r#async::reset(conn, self.discard).await
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        conn.has_broken().unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[tokio::test]
+    async fn bb8() -> crate::Result {
+        let init = crate::connection::Init::new().sql("set application_name = 'init'");
+        let manager = crate::bb8::ConnectionManager::new(&crate::test::dsn())
+            .discard_all(true)
+            .init(init);
+        let pool = bb8::Pool::builder().max_size(1).build(manager).await?;
+
+        let connection = pool.get().await.unwrap();
+        connection.execute("begin")?;
+        connection.execute("set application_name = 'bb8'")?;
+        drop(connection);
+
+        let connection = pool.get().await.unwrap();
+        assert!(!connection.transaction().is_in_transaction()?);
+        // Discarded, then initialized again
+        assert_eq!(
+            connection.query_one::<String>("show application_name", &[])?,
+            "init"
+        );
+
+        Ok(())
+    }
+}
@@ -2,6 +2,11 @@ use crate::Projectable;
 use crate::Structure;
 use std::collections::HashMap;
 
+/**
+ * Update query with the parameters of the primary key and of the data.
+ */
+type UpdateQuery<'a, P, D> = (String, Vec<&'a P>, Vec<&'a D>);
+
 #[derive(Default)]
 pub(crate) struct Param {
     pub types: Vec<u32>,
@@ -13,6 +18,12 @@ impl Param {
     pub fn values(&self) -> Vec<Option<&[u8]>> {
         self.values.iter().map(|x| x.as_deref()).collect::<Vec<_>>()
     }
+
+    pub fn extend(&mut self, other: Self) {
+        self.types.extend(other.types);
+        self.values.extend(other.values);
+        self.formats.extend(other.formats);
+    }
 }
 
 /**
@@ -22,49 +33,354 @@ impl Param {
  */
 pub type PingStatus = libpq::ping::Status;
 
+type Callback = dyn Fn(&Connection) -> crate::Result + Send + Sync;
+
+/**
+ * Initialization of new connections.
+ *
+ * SQL statements, then callbacks, are run in order when the connection is
+ * opened and after each [`Connection::reset`].
+ *
+ * ```no_run
+ * # fn main() -> elephantry::Result {
+ * let init = elephantry::connection::Init::new()
+ *     .sql("set search_path to app, public")
+ *     .sql("set timezone to 'UTC'")
+ *     .on_connect(|connection| {
+ *         connection.execute("set application_name to 'worker'")?;
+ *         Ok(())
+ *     });
+ *
+ * let connection = elephantry::Connection::with_init("", init)?;
+ * # Ok(())
+ * # }
+ * ```
+ *
+ * [`Connection::reset`]: crate::Connection::reset
+ */
+#[derive(Clone, Default)]
+pub struct Init {
+    sql: Vec<String>,
+    callbacks: Vec<std::sync::Arc<Callback>>,
+}
+
+impl Init {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Adds a SQL statement.
+     */
+    #[must_use]
+    pub fn sql(mut self, query: &str) -> Self {
+        self.sql.push(query.to_string());
+        self
+    }
+
+    /**
+     * Adds a callback, run after the SQL statements.
+     *
+     * Async connections and pools run it synchronously on the executor
+     * thread: it must not block, keep its queries short and prefer
+     * [`Init::sql`] for statements.
+     *
+     * [`Init::sql`]: #method.sql
+     */
+    #[must_use]
+    pub fn on_connect<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Connection) -> crate::Result + Send + Sync + 'static,
+    {
+        self.callbacks.push(std::sync::Arc::new(f));
+        self
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.sql.is_empty() && self.callbacks.is_empty()
+    }
+
+    pub(crate) fn run(&self, connection: &Connection) -> crate::Result {
+        for query in &self.sql {
+            connection.execute(query)?;
+        }
+
+        for callback in &self.callbacks {
+            callback(connection)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn run_async(&self, connection: &Connection) -> crate::Result {
+        for query in &self.sql {
+            connection.r#async().execute(query).await?;
+        }
+
+        for callback in &self.callbacks {
+            callback(connection)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Init {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Init")
+            .field("sql", &self.sql)
+            .field("callbacks", &self.callbacks.len())
+            .finish()
+    }
+}
+
+/**
+ * Callbacks are compared by address.
+ */
+impl PartialEq for Init {
+    fn eq(&self, other: &Self) -> bool {
+        self.sql == other.sql
+            && self.callbacks.len() == other.callbacks.len()
+            && self
+                .callbacks
+                .iter()
+                .zip(&other.callbacks)
+                .all(|(a, b)| std::sync::Arc::ptr_eq(a, b))
+    }
+}
+
+impl Eq for Init {}
+
 /**
  * A connection to a database.
  */
 #[derive(Clone, Debug)]
 pub struct Connection {
     pub(crate) connection: std::sync::Arc<std::sync::Mutex<libpq::Connection>>,
+    notices: std::sync::Arc<crate::notice::Notices>,
+    /** Notifications read but not yet returned by `notifies`. */
+    pub(crate) pending_notifies:
+        std::sync::Arc<std::sync::Mutex<std::collections::VecDeque<crate::Notify>>>,
+    pub(crate) exclusive: std::sync::Arc<crate::r#async::Exclusive>,
+    pub(crate) registration: std::sync::Arc<crate::r#async::Registration>,
+    /** Marked on writes, when this connection is a pool primary with replicas. */
+    pub(crate) last_write: Option<std::sync::Arc<crate::replica::LastWrite>>,
     pub mode: crate::pq::Format,
-}
-
-extern "C" fn notice_processor(_arg: *mut std::ffi::c_void, message: *const std::ffi::c_char) {
-    let message = unsafe { std::ffi::CStr::from_ptr(message) };
-
-    log::info!("{}", message.to_str().unwrap().trim());
+    pub(crate) init: Init,
 }
 
 impl Connection {
     pub fn new(dsn: &str) -> crate::Result<Self> {
+        Self::with_init(dsn, Init::default())
+    }
+
+    /**
+     * Creates a new connection initialized by `init`.
+     */
+    pub fn with_init(dsn: &str, init: Init) -> crate::Result<Self> {
         let connection = match libpq::Connection::new(dsn) {
             Ok(connection) => connection,
             Err(error) => {
                 return Err(crate::Error::Connect {
-                    dsn: dsn.to_string(),
+                    dsn: crate::config::redact(dsn),
+                    error,
+                });
+            }
+        };
+
+        let connection = Self::from_pq(connection, init);
+        connection.init.run(&connection)?;
+
+        Ok(connection)
+    }
+
+    /**
+     * Async version of [`Connection::new`], the connection is established
+     * without blocking the current thread.
+     *
+     * [`Connection::new`]: #method.new
+     */
+    pub async fn new_async(dsn: &str) -> crate::Result<Self> {
+        Self::new_async_with_init(dsn, Init::default()).await
+    }
+
+    /**
+     * Async version of [`Connection::with_init`].
+     *
+     * [`Connection::with_init`]: #method.with_init
+     */
+    pub async fn new_async_with_init(dsn: &str, init: Init) -> crate::Result<Self> {
+        let connection = match libpq::Connection::start(dsn) {
+            Ok(connection) => connection,
+            Err(error) => {
+                return Err(crate::Error::Connect {
+                    dsn: crate::config::redact(dsn),
                     error,
                 });
             }
         };
 
+        let connection = crate::r#async::connect(connection, libpq::Connection::poll)
+            .await
+            .map_err(|err| match err {
+                crate::Error::Async(error) => crate::Error::Connect {
+                    dsn: crate::config::redact(dsn),
+                    error,
+                },
+                err => err,
+            })?;
+
+        let connection = Self::from_pq(connection, init);
+        connection.init.run_async(&connection).await?;
+
+        Ok(connection)
+    }
+
+    /**
+     * Closes the connection and opens it again with the same parameters,
+     * then runs its [`Init`] again.
+     *
+     * [`Init`]: crate::connection::Init
+     */
+    pub fn reset(&self) -> crate::Result {
+        {
+            let connection = self
+                .connection
+                .lock()
+                .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+            connection.reset();
+
+            if connection.status() == libpq::connection::Status::Bad {
+                return Err(crate::Error::ConnectionLost(libpq::errors::Error::Backend(
+                    connection
+                        .error_message()
+                        .unwrap_or("Unable to reset the connection")
+                        .trim()
+                        .to_string(),
+                )));
+            }
+
+            Self::setup(&connection);
+        }
+
+        self.init.run(self)
+    }
+
+    fn setup(connection: &libpq::Connection) {
         connection.set_error_verbosity(libpq::Verbosity::Terse);
         connection.set_client_encoding(libpq::Encoding::UTF8);
+    }
 
-        unsafe {
-            connection.set_notice_processor(Some(notice_processor), std::ptr::null_mut());
-        }
+    fn from_pq(connection: libpq::Connection, init: Init) -> Self {
+        Self::setup(&connection);
 
-        Ok(Self {
+        let notices = std::sync::Arc::<crate::notice::Notices>::default();
+        Self::receive_notices(&connection, &notices);
+
+        Self {
             connection: std::sync::Arc::new(std::sync::Mutex::new(connection)),
+            notices,
+            pending_notifies: std::sync::Arc::default(),
+            exclusive: std::sync::Arc::default(),
+            registration: std::sync::Arc::default(),
+            last_write: None,
             mode: crate::pq::Format::Text,
-        })
+            init,
+        }
+    }
+
+    fn receive_notices(
+        connection: &libpq::Connection,
+        notices: &std::sync::Arc<crate::notice::Notices>,
+    ) {
+        unsafe {
+            connection.set_notice_receiver(
+                Some(crate::notice::notice_receiver),
+                std::sync::Arc::as_ptr(notices).cast_mut().cast(),
+            );
+        }
+    }
+
+    /**
+     * Exchanges the sessions of this connection and `other`, with their
+     * clones. Fails if a transaction is open on this one.
+     */
+    #[cfg(feature = "config")]
+    pub(crate) fn swap(&self, other: &Self) -> crate::Result {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        if connection.transaction_status() != libpq::transaction::Status::Idle {
+            return Err(crate::Error::InTransaction);
+        }
+
+        let mut other_connection = other
+            .connection
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        std::mem::swap(&mut *connection, &mut *other_connection);
+        Self::receive_notices(&connection, &self.notices);
+        Self::receive_notices(&other_connection, &other.notices);
+
+        Ok(())
+    }
+
+    /**
+     * Sends the notices and warnings of the server to `handler`, a closure or
+     * a channel sender, instead of logging them. The handler is shared by the
+     * clones of this connection and must not use it: it runs while the
+     * connection is busy.
+     */
+    pub fn set_notice_handler<H>(&self, handler: H)
+    where
+        H: crate::NoticeHandler + 'static,
+    {
+        if let Ok(mut current) = self.notices.handler.lock() {
+            *current = Some(Box::new(handler));
+        }
+    }
+
+    /**
+     * Logs the notices again.
+     */
+    pub fn clear_notice_handler(&self) {
+        if let Ok(mut current) = self.notices.handler.lock() {
+            *current = None;
+        }
+    }
+
+    /**
+     * Runs `f` and returns its result with the notices raised meanwhile, even
+     * if it fails. The notices are also sent to the notice handler.
+     */
+    pub fn collect_notices<T, F>(&self, f: F) -> (crate::Result<T>, Vec<crate::Notice>)
+    where
+        F: FnOnce(&Self) -> crate::Result<T>,
+    {
+        let collection = match crate::notice::Collection::start(&self.notices) {
+            Ok(collection) => collection,
+            Err(err) => return (Err(err), Vec::new()),
+        };
+
+        let result = f(self);
+
+        let notices = match collection.finish() {
+            Ok(notices) => notices,
+            Err(err) => return (Err(err), Vec::new()),
+        };
+
+        (result, notices)
     }
 
     #[must_use]
     pub fn r#async(&self) -> crate::Async<'_> {
-        crate::Async::new(&self.connection)
+        crate::Async::new(self)
     }
 
     #[must_use]
@@ -72,6 +388,69 @@ impl Connection {
         crate::Transaction::new(self)
     }
 
+    /**
+     * Obtains a session-level advisory lock, waiting if necessary. The lock is
+     * released when the returned guard is dropped.
+     *
+     * See <https://www.postgresql.org/docs/current/functions-admin.html#FUNCTIONS-ADVISORY-LOCKS>
+     */
+    pub fn advisory_lock<K>(
+        &self,
+        key: K,
+        mode: crate::advisory_lock::Mode,
+    ) -> crate::Result<crate::AdvisoryLock<'_>>
+    where
+        K: Into<crate::advisory_lock::Key>,
+    {
+        crate::AdvisoryLock::lock(self, key.into(), mode)
+    }
+
+    /**
+     * Likes [`advisory_lock`] but doesn’t wait for the lock to become
+     * available. Returns `None` if the lock cannot be acquired immediately.
+     *
+     * [`advisory_lock`]: #method.advisory_lock
+     */
+    pub fn try_advisory_lock<K>(
+        &self,
+        key: K,
+        mode: crate::advisory_lock::Mode,
+    ) -> crate::Result<Option<crate::AdvisoryLock<'_>>>
+    where
+        K: Into<crate::advisory_lock::Key>,
+    {
+        crate::AdvisoryLock::try_lock(self, key.into(), mode)
+    }
+
+    /**
+     * Obtains a transaction-level advisory lock, waiting if necessary. The
+     * lock is automatically released at the end of the current transaction
+     * and cannot be released explicitly.
+     */
+    pub fn advisory_xact_lock<K>(&self, key: K, mode: crate::advisory_lock::Mode) -> crate::Result
+    where
+        K: Into<crate::advisory_lock::Key>,
+    {
+        crate::advisory_lock::xact_lock(self, key.into(), mode)
+    }
+
+    /**
+     * Likes [`advisory_xact_lock`] but doesn’t wait for the lock to become
+     * available. Returns `true` if the lock was acquired.
+     *
+     * [`advisory_xact_lock`]: #method.advisory_xact_lock
+     */
+    pub fn try_advisory_xact_lock<K>(
+        &self,
+        key: K,
+        mode: crate::advisory_lock::Mode,
+    ) -> crate::Result<bool>
+    where
+        K: Into<crate::advisory_lock::Key>,
+    {
+        crate::advisory_lock::try_xact_lock(self, key.into(), mode)
+    }
+
     pub(crate) fn transaction_status(&self) -> crate::Result<libpq::transaction::Status> {
         let status = self
             .connection
@@ -112,11 +491,47 @@ impl Connection {
      * Executes a simple text query, without parameter.
      */
     pub fn execute(&self, query: &str) -> crate::Result<crate::pq::Result> {
-        self.connection
+        self.exclusive.wait_drained()?;
+
+        let connection = self
+            .connection
             .lock()
-            .map_err(|e| crate::Error::Mutex(e.to_string()))?
-            .exec(query)
-            .try_into()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        Self::rollback_dropped(&self.exclusive, &connection)?;
+        self.track_write(query);
+
+        crate::pq::Result::try_from(connection.exec(query)).map_err(|e| e.or_lost(&connection))
+    }
+
+    /**
+     * Starts the sticky window of the replicas if `query` writes.
+     */
+    pub(crate) fn track_write(&self, query: &str) {
+        if let Some(last_write) = &self.last_write
+            && crate::replica::is_write(query)
+        {
+            last_write.mark();
+        }
+    }
+
+    /**
+     * Rolls back the transaction left by a dropped [`AsyncTransaction`].
+     *
+     * [`AsyncTransaction`]: crate::AsyncTransaction
+     */
+    fn rollback_dropped(
+        exclusive: &crate::r#async::Exclusive,
+        connection: &libpq::Connection,
+    ) -> crate::Result {
+        if exclusive.take_dirty()
+            && connection.transaction_status() != libpq::transaction::Status::Idle
+        {
+            crate::pq::Result::try_from(connection.exec(&crate::transaction::rollback_query(None)))
+                .map_err(|e| e.or_lost(connection))?;
+        }
+
+        Ok(())
     }
 
     /**
@@ -153,9 +568,17 @@ impl Connection {
     ) -> crate::Result<crate::pq::Result> {
         let param = Self::transform_params(self.mode, params)?;
 
-        self.connection
+        self.exclusive.wait_drained()?;
+
+        let connection = self
+            .connection
             .lock()
-            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        Self::rollback_dropped(&self.exclusive, &connection)?;
+        self.track_write(query);
+
+        connection
             .exec_params(
                 &Self::order_parameters(query),
                 &param.types,
@@ -164,11 +587,12 @@ impl Connection {
                 crate::pq::Format::Binary,
             )
             .try_into()
+            .map_err(|e: crate::Error| e.or_lost(&connection))
     }
 
-    pub(crate) fn transform_params(
+    pub(crate) fn transform_params<P: crate::ToSql + ?Sized>(
         mode: crate::pq::Format,
-        params: &[&dyn crate::ToSql],
+        params: &[&P],
     ) -> crate::Result<Param> {
         let mut p = Param::default();
 
@@ -190,7 +614,7 @@ impl Connection {
         Ok(p)
     }
 
-    fn order_parameters(query: &str) -> std::borrow::Cow<'_, str> {
+    pub(crate) fn order_parameters(query: &str) -> std::borrow::Cow<'_, str> {
         let regex = crate::regex!(r"\$\*");
 
         let mut count = 0;
@@ -213,12 +637,33 @@ impl Connection {
     where
         M: crate::Model,
     {
-        let (clause, params) = Self::pk_clause::<M>(pk)?;
+        let (clause, params) = Self::pk_clause::<M, _>(pk)?;
         let mut tuples = self.find_where::<M>(&clause, &params, None)?;
 
         Ok(tuples.next())
     }
 
+    /**
+     * Likes [`find_by_pk`] but locks the returned row with `lock`.
+     *
+     * Returns an error if called outside of a transaction.
+     *
+     * [`find_by_pk`]: #method.find_by_pk
+     */
+    pub fn find_by_pk_locked<M>(
+        &self,
+        pk: &HashMap<&str, &dyn crate::ToSql>,
+        lock: &crate::Lock,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let (clause, params) = Self::pk_clause::<M, _>(pk)?;
+        let mut tuples = self.find_where_locked::<M>(&clause, &params, None, lock)?;
+
+        Ok(tuples.next())
+    }
+
     /**
      * Return all elements from a relation. If a suffix is given, it is append
      * to the query. This is mainly useful for "order by" statements.
@@ -255,15 +700,60 @@ impl Connection {
     where
         M: crate::Model,
     {
-        let query = format!(
-            "SELECT {} FROM {} WHERE {} {};",
+        let query = Self::find_where_query::<M>(clause, suffix, None);
+
+        self.query(&query, params)
+    }
+
+    /**
+     * Likes [`find_where`] but locks the returned rows with `lock`. The
+     * locking clause is placed after `suffix`, so it can safely be combined
+     * with "order by" or "limit" statements.
+     *
+     * Returns an error if called outside of a transaction.
+     *
+     * [`find_where`]: #method.find_where
+     */
+    pub fn find_where_locked<M>(
+        &self,
+        clause: &str,
+        params: &[&dyn crate::ToSql],
+        suffix: Option<&str>,
+        lock: &crate::Lock,
+    ) -> crate::Result<crate::Rows<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let lock = self.lock_clause(lock)?;
+        let query = Self::find_where_query::<M>(clause, suffix, Some(&lock));
+
+        self.query(&query, params)
+    }
+
+    pub(crate) fn find_where_query<M>(
+        clause: &str,
+        suffix: Option<&str>,
+        lock: Option<&str>,
+    ) -> String
+    where
+        M: crate::Model,
+    {
+        format!(
+            "SELECT {} FROM {} WHERE {} {} {};",
             M::create_projection(),
             M::Structure::relation(),
             clause,
             suffix.unwrap_or_default(),
-        );
+            lock.unwrap_or_default(),
+        )
+    }
 
-        self.query(&query, params)
+    pub(crate) fn lock_clause(&self, lock: &crate::Lock) -> crate::Result<String> {
+        if !self.transaction().is_in_transaction()? {
+            return Err(crate::Error::NotInTransaction);
+        }
+
+        lock.to_clause(self)
     }
 
     /**
@@ -285,22 +775,122 @@ impl Connection {
         max_per_page: usize,
         page: usize,
         suffix: Option<&str>,
-    ) -> crate::Result<crate::Pager<M::Entity>>
+    ) -> crate::Result<crate::Pager<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let suffix = Self::paginate_suffix(suffix, max_per_page, page);
+
+        let rows = self.find_where::<M>(clause, params, Some(&suffix))?;
+        let count = self.count_where::<M>(clause, params)?;
+
+        let pager = crate::Pager::new(rows, count, page, max_per_page);
+
+        Ok(pager)
+    }
+
+    /**
+     * Likes [`paginate_find_where`] but locks the rows of the page with
+     * `lock`.
+     *
+     * Returns an error if called outside of a transaction.
+     *
+     * [`paginate_find_where`]: #method.paginate_find_where
+     */
+    pub fn paginate_find_where_locked<M>(
+        &self,
+        clause: &str,
+        params: &[&dyn crate::ToSql],
+        max_per_page: usize,
+        page: usize,
+        suffix: Option<&str>,
+        lock: &crate::Lock,
+    ) -> crate::Result<crate::Pager<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let suffix = Self::paginate_suffix(suffix, max_per_page, page);
+
+        let rows = self.find_where_locked::<M>(clause, params, Some(&suffix), lock)?;
+        let count = self.count_where::<M>(clause, params)?;
+
+        let pager = crate::Pager::new(rows, count, page, max_per_page);
+
+        Ok(pager)
+    }
+
+    pub(crate) fn paginate_suffix(
+        suffix: Option<&str>,
+        max_per_page: usize,
+        page: usize,
+    ) -> String {
+        format!(
+            "{} offset {} fetch first {max_per_page} rows only",
+            suffix.unwrap_or_default(),
+            max_per_page * page.saturating_sub(1),
+        )
+    }
+
+    /**
+     * Show the execution plan of a statement.
+     *
+     * <div class="warning">
+     *
+     * With the `analyze` option, the statement is actually executed.
+     *
+     * </div>
+     */
+    #[cfg(feature = "json")]
+    pub fn explain(
+        &self,
+        query: &str,
+        params: &[&dyn crate::ToSql],
+        options: &crate::explain::Options,
+    ) -> crate::Result<crate::explain::Explain> {
+        let query = format!("explain ({options}) {query}");
+
+        self.query_one::<serde_json::Value>(&query, params)?
+            .try_into()
+    }
+
+    /**
+     * Show the execution plan of the query run by [`find_where`].
+     *
+     * [`find_where`]: #method.find_where
+     */
+    #[cfg(feature = "json")]
+    pub fn explain_find_where<M>(
+        &self,
+        clause: &str,
+        params: &[&dyn crate::ToSql],
+        suffix: Option<&str>,
+        options: &crate::explain::Options,
+    ) -> crate::Result<crate::explain::Explain>
     where
         M: crate::Model,
     {
-        let suffix = format!(
-            "{} offset {} fetch first {max_per_page} rows only",
-            suffix.unwrap_or_default(),
-            max_per_page * page.saturating_sub(1),
-        );
+        let query = Self::find_where_query::<M>(clause, suffix, None);
 
-        let rows = self.find_where::<M>(clause, params, Some(&suffix))?;
-        let count = self.count_where::<M>(clause, params)?;
+        self.explain(&query, params, options)
+    }
 
-        let pager = crate::Pager::new(rows, count, page, max_per_page);
+    /**
+     * Show the execution plan of the query run by [`find_by_pk`].
+     *
+     * [`find_by_pk`]: #method.find_by_pk
+     */
+    #[cfg(feature = "json")]
+    pub fn explain_find_by_pk<M>(
+        &self,
+        pk: &HashMap<&str, &dyn crate::ToSql>,
+        options: &crate::explain::Options,
+    ) -> crate::Result<crate::explain::Explain>
+    where
+        M: crate::Model,
+    {
+        let (clause, params) = Self::pk_clause::<M, _>(pk)?;
 
-        Ok(pager)
+        self.explain_find_where::<M>(&clause, &params, None, options)
     }
 
     /**
@@ -310,16 +900,22 @@ impl Connection {
     where
         M: crate::Model,
     {
-        let query = format!(
-            "SELECT COUNT(*) FROM {} WHERE {clause};",
-            M::Structure::relation(),
-        );
-
+        let query = Self::count_where_query::<M>(clause);
         let results = self.send_query(&query, params)?;
 
         results.get(0).try_get("count")
     }
 
+    pub(crate) fn count_where_query<M>(clause: &str) -> String
+    where
+        M: crate::Model,
+    {
+        format!(
+            "SELECT COUNT(*) FROM {} WHERE {clause};",
+            M::Structure::relation(),
+        )
+    }
+
     /**
      * Check if rows matching the given condition do exist or not.
      */
@@ -327,16 +923,22 @@ impl Connection {
     where
         M: crate::Model,
     {
-        let query = format!(
-            "SELECT EXISTS (SELECT true FROM {} WHERE {clause}) AS result;",
-            M::Structure::relation(),
-        );
-
+        let query = Self::exist_where_query::<M>(clause);
         let results = self.send_query(&query, params)?;
 
         results.get(0).try_get("result")
     }
 
+    pub(crate) fn exist_where_query<M>(clause: &str) -> String
+    where
+        M: crate::Model,
+    {
+        format!(
+            "SELECT EXISTS (SELECT true FROM {} WHERE {clause}) AS result;",
+            M::Structure::relation(),
+        )
+    }
+
     /**
      * Check if primary key exists.
      */
@@ -344,7 +946,7 @@ impl Connection {
     where
         M: crate::Model,
     {
-        let (clause, params) = Self::pk_clause::<M>(pk)?;
+        let (clause, params) = Self::pk_clause::<M, _>(pk)?;
 
         self.exist_where::<M>(&clause, &params)
     }
@@ -399,6 +1001,20 @@ impl Connection {
         entity: &M::Entity,
         suffix: Option<&str>,
     ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let (query, params) = Self::insert_query::<M>(entity, suffix);
+        let results = self.send_query(&query, &params)?;
+        let result = results.try_get(0).map(|x| M::create_entity(&x));
+
+        Ok(result)
+    }
+
+    pub(crate) fn insert_query<'e, M>(
+        entity: &'e M::Entity,
+        suffix: Option<&str>,
+    ) -> (String, Vec<&'e dyn crate::ToSql>)
     where
         M: crate::Model,
     {
@@ -427,10 +1043,7 @@ impl Connection {
             M::create_projection(),
         );
 
-        let results = self.send_query(&query, tuple.as_slice())?;
-        let result = results.try_get(0).map(|x| M::create_entity(&x));
-
-        Ok(result)
+        (query, tuple)
     }
 
     /**
@@ -443,6 +1056,15 @@ impl Connection {
         pk: &HashMap<&str, &dyn crate::ToSql>,
         entity: &M::Entity,
     ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        let data = Self::entity_data::<M>(entity);
+
+        self.update_by_pk::<M>(pk, &data)
+    }
+
+    pub(crate) fn entity_data<M>(entity: &M::Entity) -> HashMap<String, &dyn crate::ToSql>
     where
         M: crate::Model,
     {
@@ -458,7 +1080,7 @@ impl Connection {
             data.insert((*field).to_string(), value);
         }
 
-        self.update_by_pk::<M>(pk, &data)
+        data
     }
 
     /**
@@ -473,15 +1095,40 @@ impl Connection {
     where
         M: crate::Model,
     {
-        let (clause, mut params) = Self::pk_clause::<M>(pk)?;
+        let Some((query, mut params, data)) = Self::update_by_pk_query::<M, _, _>(pk, data)? else {
+            return Ok(None);
+        };
+        params.extend(data);
+
+        let results = self.send_query(&query, &params)?;
+
+        let entity = results.try_get(0).map(|x| M::create_entity(&x));
+
+        Ok(entity)
+    }
+
+    /**
+     * Returns `None` if there is no field to update.
+     */
+    pub(crate) fn update_by_pk_query<'a, M, P, D>(
+        pk: &HashMap<&str, &'a P>,
+        data: &HashMap<String, &'a D>,
+    ) -> crate::Result<Option<UpdateQuery<'a, P, D>>>
+    where
+        M: crate::Model,
+        P: crate::ToSql + ?Sized,
+        D: crate::ToSql + ?Sized,
+    {
+        let (clause, params) = Self::pk_clause::<M, _>(pk)?;
         let mut x = params.len() + 1;
         let mut set = Vec::new();
+        let mut values = Vec::new();
         let projection = M::default_projection();
 
         for (key, value) in data {
             if projection.has_field(key) {
                 set.push(format!("{key} = ${x}"));
-                params.push(*value);
+                values.push(*value);
                 x += 1;
             }
         }
@@ -498,11 +1145,7 @@ impl Connection {
             M::create_projection(),
         );
 
-        let results = self.send_query(&query, &params)?;
-
-        let entity = results.try_get(0).map(|x| M::create_entity(&x));
-
-        Ok(entity)
+        Ok(Some((query, params, values)))
     }
 
     /**
@@ -530,7 +1173,7 @@ impl Connection {
     where
         M: crate::Model,
     {
-        let (clause, params) = Self::pk_clause::<M>(pk)?;
+        let (clause, params) = Self::pk_clause::<M, _>(pk)?;
         let mut results = self.delete_where::<M>(&clause, &params)?;
 
         Ok(results.next())
@@ -548,20 +1191,28 @@ impl Connection {
     where
         M: crate::Model,
     {
-        let query = format!(
+        let query = Self::delete_where_query::<M>(clause);
+
+        self.query(&query, params)
+    }
+
+    pub(crate) fn delete_where_query<M>(clause: &str) -> String
+    where
+        M: crate::Model,
+    {
+        format!(
             "DELETE FROM {} WHERE {clause} RETURNING {};",
             M::Structure::relation(),
             M::create_projection(),
-        );
-
-        self.query(&query, params)
+        )
     }
 
-    fn pk_clause<'a, M>(
-        pk: &HashMap<&str, &'a dyn crate::ToSql>,
-    ) -> crate::Result<(String, Vec<&'a dyn crate::ToSql>)>
+    pub(crate) fn pk_clause<'a, M, P>(
+        pk: &HashMap<&str, &'a P>,
+    ) -> crate::Result<(String, Vec<&'a P>)>
     where
         M: crate::Model,
+        P: crate::ToSql + ?Sized,
     {
         let mut structure = M::Structure::primary_key().to_vec();
         let mut clause = String::new();
@@ -591,6 +1242,28 @@ impl Connection {
         Ok((clause, params))
     }
 
+    /**
+     * Commit a transaction that was earlier prepared for two-phase commit.
+     *
+     * See <https://www.postgresql.org/docs/current/sql-commit-prepared.html>
+     */
+    pub fn commit_prepared(&self, gid: &str) -> crate::Result {
+        let query = format!("commit prepared {}", self.escape_literal(gid)?);
+
+        self.execute(&query).map(|_| ())
+    }
+
+    /**
+     * Cancel a transaction that was earlier prepared for two-phase commit.
+     *
+     * See <https://www.postgresql.org/docs/current/sql-rollback-prepared.html>
+     */
+    pub fn rollback_prepared(&self, gid: &str) -> crate::Result {
+        let query = format!("rollback prepared {}", self.escape_literal(gid)?);
+
+        self.execute(&query).map(|_| ())
+    }
+
     /**
      * Determines if the connection is no longer usable.
      */
@@ -642,6 +1315,15 @@ impl Connection {
      * Otherwise, `None` is returned.
      */
     pub fn notifies(&self) -> crate::Result<Option<crate::Notify>> {
+        if let Some(notify) = self
+            .pending_notifies
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .pop_front()
+        {
+            return Ok(Some(notify));
+        }
+
         let connection = self
             .connection
             .lock()
@@ -651,7 +1333,94 @@ impl Connection {
         connection.notifies().map(TryFrom::try_from).transpose()
     }
 
-    fn escape_literal(&self, str: &str) -> crate::Result<String> {
+    /**
+     * Consumes the pending notifications matching `filter` and returns `true`
+     * if there was at least one. The others are kept for [`Self::notifies`].
+     */
+    #[cfg(feature = "queue")]
+    pub(crate) fn take_notifies<F>(&self, filter: F) -> crate::Result<bool>
+    where
+        F: Fn(&crate::Notify) -> bool,
+    {
+        let mut pending = self
+            .pending_notifies
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        {
+            let connection = self
+                .connection
+                .lock()
+                .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+            connection.consume_input().ok();
+
+            while let Some(notify) = connection.notifies() {
+                pending.push_back(notify.try_into()?);
+            }
+        }
+
+        let len = pending.len();
+        pending.retain(|x| !filter(x));
+
+        Ok(pending.len() != len)
+    }
+
+    /**
+     * Translates a constraint violation on the relation of `M` to the entity
+     * fields involved. Returns `None` for other errors.
+     *
+     * The constraints are read from the database: in a transaction, call it
+     * after the rollback.
+     */
+    #[cfg(feature = "inspect")]
+    pub fn validation_errors<M>(
+        &self,
+        error: &crate::Error,
+    ) -> crate::Result<Option<crate::ValidationErrors>>
+    where
+        M: crate::Model,
+    {
+        crate::validation::validation_errors::<M>(self, error)
+    }
+
+    /**
+     * Creates a logical replication slot using the `pgoutput` plugin. A
+     * temporary slot is dropped at the end of the session.
+     *
+     * The connection must be opened with `replication=database`, see the
+     * [`replication`] module.
+     *
+     * [`replication`]: crate::replication
+     */
+    pub fn create_replication_slot(
+        &self,
+        name: &str,
+        temporary: bool,
+    ) -> crate::Result<crate::replication::Slot> {
+        crate::replication::create_slot(self, name, temporary)
+    }
+
+    /**
+     * Drops a replication slot, waiting for it to become inactive.
+     */
+    pub fn drop_replication_slot(&self, name: &str) -> crate::Result {
+        crate::replication::drop_slot(self, name)
+    }
+
+    /**
+     * Starts streaming the changes of `slot` from the position `start`.
+     */
+    pub fn start_replication(
+        &self,
+        slot: &str,
+        start: crate::Lsn,
+        options: &crate::replication::Options,
+    ) -> crate::Result<crate::replication::Stream<'_>> {
+        crate::replication::start(self, slot, start, options)
+    }
+
+    pub(crate) fn escape_literal(&self, str: &str) -> crate::Result<String> {
         self.connection
             .lock()
             .map_err(|e| crate::Error::Mutex(e.to_string()))?
@@ -694,73 +1463,7 @@ impl Connection {
             .map_err(|e| crate::Error::Mutex(e.to_string()))?;
         let info = connection.info()?;
 
-        let config = crate::Config {
-            application_name: info.get("application_name").and_then(|x| x.val.clone()),
-            channel_binding: Self::config_get(&info, "channel_binding")?,
-            client_encoding: info.get("client_encoding").and_then(|x| x.val.clone()),
-            connect_timeout: Self::config_get(&info, "connect_timeout")?,
-            dbname: info.get("dbname").and_then(|x| x.val.clone()),
-            fallback_application_name: info
-                .get("fallback_application_name")
-                .and_then(|x| x.val.clone()),
-            gssencmode: Self::config_get(&info, "gssencmode")?,
-            gsslib: info.get("gsslib").and_then(|x| x.val.clone()),
-            hostaddr: info.get("hostaddr").and_then(|x| x.val.clone()),
-            host: info.get("host").and_then(|x| x.val.clone()),
-            keepalives_count: Self::config_get(&info, "keepalives_count")?,
-            keepalives_idle: Self::config_get(&info, "keepalives_idle")?,
-            keepalives_interval: Self::config_get(&info, "keepalives_interval")?,
-            keepalives: Self::config_get::<i32>(&info, "keepalives")?.map(|x| x == 1),
-            krbsrvname: info.get("krbsrvname").and_then(|x| x.val.clone()),
-            #[cfg(feature = "pg16")]
-            load_balance_hosts: Self::config_get(&info, "load_balance_hosts")?,
-            options: info.get("options").and_then(|x| x.val.clone()),
-            passfile: info.get("passfile").and_then(|x| x.val.clone()),
-            password: info.get("password").and_then(|x| x.val.clone()),
-            port: info.get("port").and_then(|x| x.val.clone()),
-            replication: info.get("replication").and_then(|x| x.val.clone()),
-            requirepeer: info.get("requirepeer").and_then(|x| x.val.clone()),
-            #[cfg(feature = "pg16")]
-            require_auth: info.get("require_auth").and_then(|x| x.val.clone()),
-            service: info.get("service").and_then(|x| x.val.clone()),
-            sslcert: info.get("sslcert").and_then(|x| x.val.clone()),
-            #[cfg(feature = "pg16")]
-            sslcertmode: Self::config_get(&info, "sslcertmode")?,
-            sslcompression: Self::config_get::<i32>(&info, "sslcompression")?.map(|x| x == 1),
-            #[cfg(feature = "pg17")]
-            sslnegotiation: Self::config_get(&info, "sslnegotiation")?,
-            sslcrl: info.get("sslcrl").and_then(|x| x.val.clone()),
-            sslkey: info.get("sslkey").and_then(|x| x.val.clone()),
-            ssl_max_protocol_version: info
-                .get("ssl_max_protocol_version")
-                .and_then(|x| x.val.clone()),
-            ssl_min_protocol_version: info
-                .get("ssl_min_protocol_version")
-                .and_then(|x| x.val.clone()),
-            sslmode: Self::config_get(&info, "sslmode")?,
-            sslpassword: info.get("sslpassword").and_then(|x| x.val.clone()),
-            sslrootcert: info.get("sslrootcert").and_then(|x| x.val.clone()),
-            target_session_attrs: Self::config_get(&info, "target_session_attrs")?,
-            tcp_user_timeout: Self::config_get(&info, "tcp_user_timeout")?,
-            user: info.get("user").and_then(|x| x.val.clone()),
-        };
-
-        Ok(config)
-    }
-
-    fn config_get<T>(
-        info: &HashMap<String, libpq::connection::Info>,
-        name: &str,
-    ) -> Result<Option<T>, <T as std::str::FromStr>::Err>
-    where
-        T: std::str::FromStr,
-    {
-        let r = match info.get(name).map(|x| x.val.clone()) {
-            Some(Some(val)) => Some(val.parse()?),
-            _ => None,
-        };
-
-        Ok(r)
+        crate::Config::from_info(info.values())
     }
 
     /**
@@ -771,51 +1474,16 @@ impl Connection {
         I: Iterator<Item = M::Entity>,
         M: crate::Model,
     {
-        use crate::Entity;
-
-        let projection = M::default_projection();
-        let field_names = projection.field_names();
+        let field_names = M::default_projection().field_names();
+        self.execute(&Self::copy_query::<M>(&field_names))?;
 
-        let query = format!(
-            "copy {} ({}) from stdin (format binary);",
-            M::Structure::relation(),
-            field_names.join(", "),
-        );
-        self.execute(&query)?;
+        let buf = Self::copy_buffer(entities, &field_names)?;
 
         let connection = self
             .connection
             .lock()
             .map_err(|e| crate::Error::Mutex(e.to_string()))?;
 
-        // Signature
-        let mut buf = vec![
-            b'P', b'G', b'C', b'O', b'P', b'Y', b'\n', 255, b'\r', b'\n', b'\0',
-        ];
-        // Flags field
-        crate::to_sql::write_i32(&mut buf, 0)?;
-        // Header extension area length
-        crate::to_sql::write_i32(&mut buf, 0)?;
-
-        for entity in entities {
-            crate::to_sql::write_i16(&mut buf, field_names.len() as i16)?;
-
-            for field in &field_names {
-                let value = match entity.get(field) {
-                    Some(value) => value.to_binary()?,
-                    None => None,
-                };
-
-                if let Some(mut value) = value {
-                    crate::to_sql::write_i32(&mut buf, value.len() as i32)?;
-                    buf.append(&mut value);
-                } else {
-                    crate::to_sql::write_i32(&mut buf, -1)?;
-                }
-            }
-        }
-        crate::to_sql::write_i16(&mut buf, -1)?;
-
         connection.put_copy_data(&buf).map_err(crate::Error::Copy)?;
 
         connection.put_copy_end(None).map_err(crate::Error::Copy)?;
@@ -830,8 +1498,85 @@ impl Connection {
 
         Ok(())
     }
+
+    pub(crate) fn copy_query<M>(field_names: &[String]) -> String
+    where
+        M: crate::Model,
+    {
+        format!(
+            "copy {} ({}) from stdin (format binary);",
+            M::Structure::relation(),
+            field_names.join(", "),
+        )
+    }
+
+    /**
+     * Encodes entities in the binary COPY format.
+     */
+    pub(crate) fn copy_buffer<E, I>(entities: I, field_names: &[String]) -> crate::Result<Vec<u8>>
+    where
+        I: Iterator<Item = E>,
+        E: crate::Entity,
+    {
+        let mut buf = Self::copy_header()?;
+
+        for entity in entities {
+            Self::copy_row(&mut buf, &entity, field_names)?;
+        }
+
+        Self::copy_trailer(&mut buf)?;
+
+        Ok(buf)
+    }
+
+    pub(crate) fn copy_header() -> crate::Result<Vec<u8>> {
+        // Signature
+        let mut buf = COPY_SIGNATURE.to_vec();
+        // Flags field
+        crate::to_sql::write_i32(&mut buf, 0)?;
+        // Header extension area length
+        crate::to_sql::write_i32(&mut buf, 0)?;
+
+        Ok(buf)
+    }
+
+    /**
+     * Encodes the `field_names` of `entity` as a row of the binary COPY format.
+     */
+    pub(crate) fn copy_row<E>(
+        buf: &mut Vec<u8>,
+        entity: &E,
+        field_names: &[String],
+    ) -> crate::Result
+    where
+        E: crate::Entity,
+    {
+        crate::to_sql::write_i16(buf, field_names.len() as i16)?;
+
+        for field in field_names {
+            let value = match entity.get(field) {
+                Some(value) => value.to_binary()?,
+                None => None,
+            };
+
+            if let Some(mut value) = value {
+                crate::to_sql::write_i32(buf, value.len() as i32)?;
+                buf.append(&mut value);
+            } else {
+                crate::to_sql::write_i32(buf, -1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn copy_trailer(buf: &mut Vec<u8>) -> crate::Result {
+        crate::to_sql::write_i16(buf, -1)
+    }
 }
 
+pub(crate) const COPY_SIGNATURE: &[u8] = b"PGCOPY\n\xff\r\n\0";
+
 #[cfg(test)]
 mod test {
     #[derive(elephantry_derive::Entity)]
@@ -851,7 +1596,7 @@ mod test {
         ];
 
         for pk in pks {
-            let (clause, params) = crate::Connection::pk_clause::<Model>(&pk)?;
+            let (clause, params) = crate::Connection::pk_clause::<Model, _>(&pk)?;
 
             assert_eq!(clause, "\"db\" = $1 AND \"id\" = $2");
 
@@ -869,13 +1614,96 @@ mod test {
     fn missing_key() {
         let pk = crate::pk! { db => "" };
 
-        assert!(crate::Connection::pk_clause::<Model>(&pk).is_err());
+        assert!(crate::Connection::pk_clause::<Model, _>(&pk).is_err());
     }
 
     #[test]
     fn extra_key() {
         let pk = crate::pk! { db => "", id => "", extra => "" };
 
-        assert!(crate::Connection::pk_clause::<Model>(&pk).is_err());
+        assert!(crate::Connection::pk_clause::<Model, _>(&pk).is_err());
+    }
+
+    fn two_phase(gid: &str, commit: bool) -> crate::Result<i64> {
+        let connection = crate::Connection::new(&crate::test::dsn())?;
+        let table = gid.replace('-', "_");
+
+        connection.execute(&format!("create table {table} (id int)"))?;
+
+        let transaction = connection.transaction();
+        transaction.start()?;
+        connection.execute(&format!("insert into {table} values (1)"))?;
+        transaction.prepare(gid)?;
+
+        // The prepared transaction is detached from the session.
+        assert_eq!(
+            connection.query_one::<i64>(&format!("select count(*) from {table}"), &[])?,
+            0
+        );
+
+        if commit {
+            connection.commit_prepared(gid)?;
+        } else {
+            connection.rollback_prepared(gid)?;
+        }
+
+        let count = connection.query_one::<i64>(&format!("select count(*) from {table}"), &[]);
+        connection.execute(&format!("drop table {table}"))?;
+
+        count
+    }
+
+    #[test]
+    #[ignore = "requires max_prepared_transactions > 0"]
+    fn commit_prepared() -> crate::Result {
+        assert_eq!(two_phase("elephantry-commit-prepared", true)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires max_prepared_transactions > 0"]
+    fn rollback_prepared() -> crate::Result {
+        assert_eq!(two_phase("elephantry-rollback-prepared", false)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn init() -> crate::Result {
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+
+        let init = super::Init::new()
+            .sql("set application_name = 'init'")
+            .on_connect(move |connection| {
+                counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                connection.execute("set search_path to pg_catalog")?;
+                Ok(())
+            });
+        let connection = crate::Connection::with_init(&crate::test::dsn(), init)?;
+
+        let show = |name: &str| connection.query_one::<String>(&format!("show {name}"), &[]);
+
+        assert_eq!(show("application_name")?, "init");
+        assert_eq!(show("search_path")?, "pg_catalog");
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 1);
+
+        connection.execute("set application_name = 'changed'")?;
+        let pid = connection.query_one::<i32>("select pg_backend_pid()", &[])?;
+        connection.reset()?;
+
+        assert_ne!(
+            connection.query_one::<i32>("select pg_backend_pid()", &[])?,
+            pid
+        );
+        assert_eq!(show("application_name")?, "init");
+        assert_eq!(show("search_path")?, "pg_catalog");
+        assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 2);
+
+        let init = super::Init::new().sql("select 1/0");
+        assert!(crate::Connection::with_init(&crate::test::dsn(), init).is_err());
+
+        Ok(())
     }
 }
@@ -0,0 +1,171 @@
+/**
+ * [deadpool](https://crates.io/crates/deadpool) manager.
+ *
+ * ```no_run
+ * # fn main() -> elephantry::Result {
+ * # smol::block_on(async {
+ * let manager = elephantry::deadpool::ConnectionManager::new("postgres://localhost");
+ * let pool = deadpool::managed::Pool::<elephantry::deadpool::ConnectionManager>::builder(manager)
+ *     .build()
+ *     .unwrap();
+ *
+ * let connection = pool.get().await.unwrap();
+ * connection.r#async().execute("select 1").await?;
+ * # Ok(())
+ * # })
+ * # }
+ * ```
+ */
+#[derive(Clone, Debug)]
+pub struct ConnectionManager {
+    dsn: String,
+    discard: bool,
+    init: crate::connection::Init,
+}
+
+impl ConnectionManager {
+    #[must_use]
+    pub fn new(dsn: &str) -> Self {
+        Self {
+            dsn: dsn.to_string(),
+            discard: false,
+            init: crate::connection::Init::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn from_config(config: &crate::Config) -> Self {
+        Self::new(&config.to_string())
+    }
+
+    /**
+     * Resets the session state with `DISCARD ALL` when a connection is
+     * recycled.
+     */
+    #[must_use]
+    pub fn discard_all(mut self, discard: bool) -> Self {
+        self.discard = discard;
+        self
+    }
+
+    /**
+     * Initialization of each new connection, run again after `DISCARD ALL`.
+     */
+    #[must_use]
+    pub fn init(mut self, init: crate::connection::Init) -> Self {
+        self.init = init;
+        self
+    }
+}
+
+impl deadpool::managed::Manager for ConnectionManager {
+    type Type = crate::Connection;
+    type Error = crate::Error;
+
+    async fn create(&self) -> crate::Result<Self::Type> {
+        crate::Connection::new_async_with_init(&self.dsn, self.init.clone()).await
+    }
+
+    async fn recycle(
+        &self,
+        conn: &mut Self::Type,
+        _: &deadpool::managed::Metrics,
+    ) -> deadpool::managed::RecycleResult<Self::Error> {
+        if conn.has_broken()? {
+            return Err(deadpool::managed::RecycleError::message(
+                "Connection is broken",
+            ));
+        }
+
+        crate::r#async::reset(conn, self.discard).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    fn pool(discard: bool) -> deadpool::managed::Pool<crate::deadpool::ConnectionManager> {
+        let manager =
+            crate::deadpool::ConnectionManager::new(&crate::test::dsn()).discard_all(discard);
+
+        deadpool::managed::Pool::builder(manager)
+            .max_size(1)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn deadpool() -> crate::Result {
+        let pool = pool(false);
+
+        smol::block_on(async {
+            let connection = pool.get().await.unwrap();
+            connection.execute("begin")?;
+            connection.execute("set application_name = 'deadpool'")?;
+            drop(connection);
+
+            let connection = pool.get().await.unwrap();
+            assert!(!connection.transaction().is_in_transaction()?);
+            connection.execute("set application_name = 'deadpool'")?;
+            drop(connection);
+
+            let connection = pool.get().await.unwrap();
+            assert_eq!(
+                connection.query_one::<String>("show application_name", &[])?,
+                "deadpool"
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn discard_all() -> crate::Result {
+        let pool = pool(true);
+
+        smol::block_on(async {
+            let connection = pool.get().await.unwrap();
+            connection.execute("set application_name = 'deadpool'")?;
+            drop(connection);
+
+            let connection = pool.get().await.unwrap();
+            assert_eq!(
+                connection.query_one::<String>("show application_name", &[])?,
+                ""
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn init_after_recycle() -> crate::Result {
+        let init = crate::connection::Init::new().sql("set application_name = 'init'");
+        let manager = crate::deadpool::ConnectionManager::new(&crate::test::dsn())
+            .discard_all(true)
+            .init(init);
+        let pool = deadpool::managed::Pool::<crate::deadpool::ConnectionManager>::builder(manager)
+            .max_size(1)
+            .build()
+            .unwrap();
+
+        smol::block_on(async {
+            let connection = pool.get().await.unwrap();
+            assert_eq!(
+                connection.query_one::<String>("show application_name", &[])?,
+                "init"
+            );
+            connection.execute("set application_name = 'deadpool'")?;
+            drop(connection);
+
+            let connection = pool.get().await.unwrap();
+            assert_eq!(
+                connection.query_one::<String>("show application_name", &[])?,
+                "init"
+            );
+
+            Ok(())
+        })
+    }
+}
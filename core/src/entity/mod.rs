@@ -15,6 +15,13 @@ use std::collections::HashMap;
 pub trait Entity {
     /** Create a new struct from SQL result. */
     fn from(tuple: &crate::Tuple<'_>) -> Self;
+    /** Like [`Entity::from`], but returns the decoding errors. */
+    fn try_from(tuple: &crate::Tuple<'_>) -> crate::Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self::from(tuple))
+    }
     /** Get the value of the field named `field`. */
     fn get(&self, field: &str) -> Option<&dyn crate::ToSql>;
 }
@@ -37,6 +44,19 @@ impl<T: crate::FromSql + crate::ToSql, S: std::hash::BuildHasher + Default> Enti
         hashmap
     }
 
+    fn try_from(tuple: &crate::Tuple<'_>) -> crate::Result<Self> {
+        let mut hashmap = HashMap::default();
+
+        for x in 0..tuple.len() {
+            let Some(name) = tuple.field_name(x)? else {
+                continue;
+            };
+            hashmap.insert(name, tuple.try_nth(x)?);
+        }
+
+        Ok(hashmap)
+    }
+
     fn get(&self, field: &str) -> Option<&dyn crate::ToSql> {
         HashMap::get(self, field).map(|x| x as &dyn crate::ToSql)
     }
@@ -56,6 +76,12 @@ impl<T: crate::FromSql + crate::ToSql, S: std::hash::BuildHasher + Default> Enti
         hashmap
     }
 
+    fn try_from(tuple: &crate::Tuple<'_>) -> crate::Result<Self> {
+        (0..tuple.len())
+            .map(|x| Ok((x, tuple.try_nth(x)?)))
+            .collect()
+    }
+
     fn get(&self, field: &str) -> Option<&dyn crate::ToSql> {
         let x = match field.parse::<usize>() {
             Ok(x) => x,
@@ -24,6 +24,10 @@ impl<T: Simple> crate::Entity for T {
         tuple.nth(0)
     }
 
+    fn try_from(tuple: &crate::Tuple<'_>) -> crate::Result<T> {
+        tuple.try_nth(0)
+    }
+
     fn get(&self, _: &str) -> Option<&dyn crate::ToSql> {
         Some(self)
     }
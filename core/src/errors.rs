@@ -2,6 +2,9 @@ pub type Result<T = ()> = std::result::Result<T, crate::Error>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    /** Advisory lock not held by the current session */
+    #[error("Advisory lock {0:?} is not held")]
+    AdvisoryLock(crate::advisory_lock::Key),
     /** An error in async context. */
     #[error("Async error: {0}")]
     Async(libpq::errors::Error),
@@ -19,6 +22,9 @@ pub enum Error {
         dsn: String,
         error: libpq::errors::Error,
     },
+    /** Connection to the server lost */
+    #[error("Connection lost: {0}")]
+    ConnectionLost(libpq::errors::Error),
     /** Copy error */
     #[error("Copy error: {0}")]
     Copy(libpq::errors::Error),
@@ -37,6 +43,9 @@ pub enum Error {
     /** Inspector error */
     #[error("{0}")]
     Inspect(String),
+    /** Operation forbidden inside a transaction */
+    #[error("This operation must be done outside of a transaction")]
+    InTransaction,
     /** Input/Output error */
     #[error(transparent)]
     Io(#[from] std::io::Error),
@@ -52,6 +61,12 @@ pub enum Error {
     /** Connection mutex poisoned */
     #[error("Mutex error: {0}")]
     Mutex(String),
+    /** Entity not found */
+    #[error("Not found")]
+    NotFound,
+    /** Operation requiring a transaction */
+    #[error("This operation must be done inside a transaction")]
+    NotInTransaction,
     /** Fetch a null value in a non-option type */
     #[error("Try to retreive null field as non-option type")]
     NotNull,
@@ -67,11 +82,17 @@ pub enum Error {
     /** Ping error */
     #[error("Ping error: {0:?}")]
     Ping(crate::connection::PingStatus),
+    /** No connection available in the pool before the acquire timeout */
+    #[error("Timed out waiting for a connection from the pool")]
+    PoolTimeout,
     /** Incomplete primary key */
     #[error("Invalid primary key")]
     PrimaryKey,
-    /** SQL error */
-    #[error("{}", .0.error_message().unwrap().unwrap_or_else(|| "Unknow SQL error".to_string()))]
+    /** Invalid logical replication message */
+    #[error("Replication error: {0}")]
+    Replication(String),
+    /** SQL error, see [`Error::db_error`] */
+    #[error("{}", sql_message(.0))]
     Sql(crate::pq::Result),
     /** Unable to transform a rust value to SQL */
     #[error("Invalid convertion from {} to {rust_type}: {message}", .pg_type.name)]
@@ -83,6 +104,9 @@ pub enum Error {
     /** TryFrom int error */
     #[error(transparent)]
     TryFromIntError(#[from] std::num::TryFromIntError),
+    /** Unchanged TOASTed value of a logical replication row */
+    #[error("Unchanged TOASTed value of column {0}")]
+    Unchanged(String),
     /** UTF8 error */
     #[error(transparent)]
     Utf8(#[from] std::string::FromUtf8Error),
@@ -91,3 +115,339 @@ pub enum Error {
     #[error(transparent)]
     Xml(#[from] xmltree::Error),
 }
+
+impl Error {
+    /**
+     * Fields of the error reported by the server.
+     */
+    #[must_use]
+    pub fn db_error(&self) -> Option<DbError> {
+        match self {
+            Self::Sql(result) => DbError::from_result(result),
+            _ => None,
+        }
+    }
+
+    /**
+     * SQLSTATE code of the error reported by the server.
+     */
+    #[must_use]
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Self::Sql(result) => result
+                .error_field(libpq::result::ErrorField::Sqlstate)
+                .ok()
+                .flatten(),
+            _ => None,
+        }
+    }
+
+    /**
+     * A unique constraint is violated.
+     */
+    #[must_use]
+    pub fn is_unique_violation(&self) -> bool {
+        self.code() == Some("23505")
+    }
+
+    /**
+     * A foreign key constraint is violated.
+     */
+    #[must_use]
+    pub fn is_foreign_key_violation(&self) -> bool {
+        self.code() == Some("23503")
+    }
+
+    /**
+     * The transaction can’t be serialized with the concurrent ones and should
+     * be retried.
+     */
+    #[must_use]
+    pub fn is_serialization_failure(&self) -> bool {
+        self.code() == Some("40001")
+    }
+
+    /**
+     * The connection to the server failed or was lost.
+     */
+    #[must_use]
+    pub fn is_connection_error(&self) -> bool {
+        match self {
+            Self::Connect { .. } | Self::ConnectionLost(_) | Self::Ping(_) => true,
+            _ => self.code().is_some_and(|code| {
+                code.starts_with("08") || matches!(code, "57P01" | "57P02" | "57P03")
+            }),
+        }
+    }
+
+    /**
+     * Reports the error as [`Error::ConnectionLost`] if `connection` is
+     * broken and the server didn’t send a SQLSTATE.
+     */
+    pub(crate) fn or_lost(self, connection: &libpq::Connection) -> Self {
+        if connection.status() != libpq::connection::Status::Bad || self.code().is_some() {
+            return self;
+        }
+
+        match self {
+            Self::Async(error) | Self::Copy(error) | Self::Libpq(error) => {
+                Self::ConnectionLost(error)
+            }
+            Self::Sql(_) => Self::ConnectionLost(
+                connection
+                    .error_message()
+                    .map(|x| libpq::errors::Error::Backend(x.trim_end().to_string()))
+                    .unwrap_or(libpq::errors::Error::Unknow),
+            ),
+            error => error,
+        }
+    }
+}
+
+fn sql_message(result: &crate::pq::Result) -> String {
+    match DbError::from_result(result) {
+        Some(error) => error.to_string(),
+        None => result.error_message().ok().flatten().map_or_else(
+            || "Unknow SQL error".to_string(),
+            |x| x.trim_end().to_string(),
+        ),
+    }
+}
+
+/**
+ * Error reported by the server, see
+ * <https://www.postgresql.org/docs/current/protocol-error-fields.html>
+ */
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DbError {
+    /** SQLSTATE code, see [`pq::State`](crate::pq::State). */
+    pub code: String,
+    /** `ERROR`, `FATAL` or `PANIC`, not localized. */
+    pub severity: String,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    /** Position of the error in the query, in characters from 1. */
+    pub position: Option<u32>,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    pub datatype: Option<String>,
+    pub constraint: Option<String>,
+}
+
+impl DbError {
+    /**
+     * Returns `None` for errors raised by libpq itself.
+     */
+    pub(crate) fn from_result(result: &libpq::Result) -> Option<Self> {
+        use libpq::result::ErrorField;
+
+        let field = |field| {
+            result
+                .error_field(field)
+                .ok()
+                .flatten()
+                .map(ToString::to_string)
+        };
+
+        let error = Self {
+            code: field(ErrorField::Sqlstate)?,
+            severity: field(ErrorField::SeverityNonlocalized)
+                .or_else(|| field(ErrorField::Severity))
+                .unwrap_or_default(),
+            message: field(ErrorField::MessagePrimary).unwrap_or_default(),
+            detail: field(ErrorField::MessageDetail),
+            hint: field(ErrorField::MessageHint),
+            position: field(ErrorField::StatementPosition).and_then(|x| x.parse().ok()),
+            schema: field(ErrorField::SchemaName),
+            table: field(ErrorField::TableName),
+            column: field(ErrorField::ColumnName),
+            datatype: field(ErrorField::DatatypeName),
+            constraint: field(ErrorField::ConstraintName),
+        };
+
+        Some(error)
+    }
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:  {}", self.severity, self.message)?;
+
+        if let Some(detail) = &self.detail {
+            write!(f, "\nDETAIL:  {detail}")?;
+        }
+
+        if let Some(hint) = &self.hint {
+            write!(f, "\nHINT:  {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "actix", feature = "axum"))]
+impl Error {
+    /**
+     * HTTP status code matching this error.
+     */
+    pub(crate) fn status_code(&self) -> u16 {
+        if self.is_connection_error() {
+            return 503;
+        }
+
+        match self {
+            Self::NotFound => 404,
+            Self::PrimaryKey => 400,
+            Self::PoolTimeout => 503,
+            Self::Sql(_) => match self.code() {
+                Some(code) => match code {
+                    "40001" | "40P01" => 409,
+                    "42501" => 403,
+                    code if code.starts_with("23") => 409,
+                    code if code.starts_with("22") => 400,
+                    code if code.starts_with("57P") => 503,
+                    _ => 500,
+                },
+                None => 500,
+            },
+            _ => 500,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn db_error() -> crate::Result {
+        let connection = crate::Connection::new(&crate::test::dsn())?;
+        connection.execute(
+            "create temporary table error_parent (id int primary key);
+            create temporary table error_child (
+                parent_id int constraint error_child_parent_fk references error_parent
+            );
+            insert into error_parent values (1);",
+        )?;
+
+        let err = connection
+            .execute("insert into error_parent values (1)")
+            .unwrap_err();
+        assert!(err.is_unique_violation());
+        assert!(!err.is_foreign_key_violation());
+
+        let error = err.db_error().unwrap();
+        assert_eq!(error.code, "23505");
+        assert_eq!(error.severity, "ERROR");
+        assert_eq!(error.table.as_deref(), Some("error_parent"));
+        assert_eq!(error.constraint.as_deref(), Some("error_parent_pkey"));
+        assert_eq!(
+            error.detail.as_deref(),
+            Some("Key (id)=(1) already exists.")
+        );
+        assert_eq!(
+            err.to_string(),
+            "ERROR:  duplicate key value violates unique constraint \"error_parent_pkey\"\nDETAIL:  Key (id)=(1) already exists."
+        );
+
+        let err = connection
+            .execute("insert into error_child values (2)")
+            .unwrap_err();
+        assert!(err.is_foreign_key_violation());
+        assert_eq!(
+            err.db_error().unwrap().constraint.as_deref(),
+            Some("error_child_parent_fk")
+        );
+
+        let err = connection
+            .execute("select unknown from error_parent")
+            .unwrap_err();
+        let error = err.db_error().unwrap();
+        assert_eq!(error.code, "42703");
+        assert_eq!(error.position, Some(8));
+
+        let err = connection
+            .execute("do $$ begin raise exception using errcode = 'serialization_failure', hint = 'Retry'; end $$")
+            .unwrap_err();
+        assert!(err.is_serialization_failure());
+        assert_eq!(err.db_error().unwrap().hint.as_deref(), Some("Retry"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn connection_error() {
+        let err = crate::Connection::new("host=/nonexistent").unwrap_err();
+
+        assert!(err.is_connection_error());
+        assert!(err.db_error().is_none());
+    }
+
+    #[test]
+    #[cfg(any(feature = "actix", feature = "axum"))]
+    fn status_code() -> crate::Result {
+        let connection = crate::test::new_conn()?;
+
+        let err = crate::Connection::new("host=/nonexistent").unwrap_err();
+        assert_eq!(err.status_code(), 503);
+
+        let err = crate::Error::ConnectionLost(libpq::errors::Error::Backend(
+            "server closed the connection unexpectedly".to_string(),
+        ));
+        assert_eq!(err.status_code(), 503);
+
+        let err = connection
+            .execute("do $$ begin raise exception using errcode = 'connection_failure'; end $$")
+            .unwrap_err();
+        assert_eq!(err.status_code(), 503);
+
+        let err = connection
+            .execute("do $$ begin raise exception using errcode = 'unique_violation'; end $$")
+            .unwrap_err();
+        assert_eq!(err.status_code(), 409);
+
+        assert_eq!(crate::Error::NotFound.status_code(), 404);
+
+        Ok(())
+    }
+
+    #[test]
+    fn connection_lost() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        let pid = conn.query_one::<i32>("select pg_backend_pid()", &[])?;
+
+        crate::test::new_conn()?.query_one::<bool>("select pg_terminate_backend($1)", &[&pid])?;
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let err = conn.execute("select 1").unwrap_err();
+        assert!(err.is_connection_error(), "{err:?}");
+
+        let err = conn.query::<i32>("select $1", &[&1]).unwrap_err();
+        assert!(err.is_connection_error(), "{err:?}");
+
+        conn.reset()?;
+        conn.execute("select 1")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn async_connection_lost() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        let pid = conn.query_one::<i32>("select pg_backend_pid()", &[])?;
+
+        crate::test::new_conn()?.query_one::<bool>("select pg_terminate_backend($1)", &[&pid])?;
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        smol::block_on(async {
+            let err = conn.r#async().execute("select 1").await.unwrap_err();
+            assert!(err.is_connection_error(), "{err:?}");
+
+            let err = conn.r#async().execute("select 1").await.unwrap_err();
+            assert!(err.is_connection_error(), "{err:?}");
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,564 @@
+/*!
+ * Query plan inspection, via `EXPLAIN (FORMAT JSON)`.
+ *
+ * See <https://www.postgresql.org/docs/current/sql-explain.html>
+ */
+
+use std::collections::HashMap;
+
+/**
+ * `EXPLAIN` options.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Options {
+    /**
+     * Carry out the command and show actual run times.
+     *
+     * <div class="warning">
+     *
+     * The statement is actually executed, wrap data-modifying statement in
+     * a rollbacked transaction.
+     *
+     * </div>
+     */
+    pub analyze: bool,
+    /**
+     * Include information on buffer usage.
+     */
+    pub buffers: bool,
+    /**
+     * Display additional information, like output columns.
+     */
+    pub verbose: bool,
+    /**
+     * Include information on modified configuration parameters.
+     */
+    pub settings: bool,
+}
+
+impl std::fmt::Display for Options {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("format json")?;
+
+        if self.analyze {
+            f.write_str(", analyze")?;
+        }
+        if self.buffers {
+            f.write_str(", buffers")?;
+        }
+        if self.verbose {
+            f.write_str(", verbose")?;
+        }
+        if self.settings {
+            f.write_str(", settings")?;
+        }
+
+        Ok(())
+    }
+}
+
+/**
+ * Result of [`Connection::explain`].
+ *
+ * Its `Display` implementation renders the plan like the `EXPLAIN` text
+ * format.
+ *
+ * [`Connection::explain`]: crate::Connection::explain
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explain {
+    pub plan: Plan,
+    /** Planning time in milliseconds, with `analyze` option. */
+    pub planning_time: Option<f64>,
+    /** Execution time in milliseconds, with `analyze` option. */
+    pub execution_time: Option<f64>,
+    /** Modified configuration parameters, with `settings` option. */
+    pub settings: HashMap<String, String>,
+}
+
+/**
+ * Number of blocks hit, read, dirtied or written.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Buffers {
+    pub shared_hit: i64,
+    pub shared_read: i64,
+    pub shared_dirtied: i64,
+    pub shared_written: i64,
+    pub local_hit: i64,
+    pub local_read: i64,
+    pub local_dirtied: i64,
+    pub local_written: i64,
+    pub temp_read: i64,
+    pub temp_written: i64,
+}
+
+/**
+ * A node of the plan tree.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    pub node_type: String,
+    pub relation_name: Option<String>,
+    pub schema: Option<String>,
+    pub alias: Option<String>,
+    pub index_name: Option<String>,
+    pub startup_cost: f64,
+    pub total_cost: f64,
+    pub plan_rows: f64,
+    pub plan_width: i64,
+    pub actual_startup_time: Option<f64>,
+    pub actual_total_time: Option<f64>,
+    pub actual_rows: Option<f64>,
+    pub actual_loops: Option<f64>,
+    /** With `buffers` option. */
+    pub buffers: Option<Buffers>,
+    /** Output columns, with `verbose` option. */
+    pub output: Vec<String>,
+    /** Children nodes. */
+    pub plans: Vec<Plan>,
+    /** Other node properties (conditions, sort keys, …). */
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/**
+ * Properties rendered as detail lines by the text renderer.
+ */
+const DETAILS: &[&str] = &[
+    "Sort Key",
+    "Group Key",
+    "Hash Cond",
+    "Merge Cond",
+    "Join Filter",
+    "Index Cond",
+    "Recheck Cond",
+    "Filter",
+    "Rows Removed by Filter",
+];
+
+impl Plan {
+    /**
+     * Iterates over this node and all its descendants, depth first.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = &Plan> {
+        let mut stack = vec![self];
+
+        std::iter::from_fn(move || {
+            let plan = stack.pop()?;
+            stack.extend(plan.plans.iter().rev());
+
+            Some(plan)
+        })
+    }
+
+    fn fmt_node(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        indent: &str,
+        child: bool,
+    ) -> std::fmt::Result {
+        let arrow = if child { "->  " } else { "" };
+
+        write!(f, "{indent}{arrow}{}", self.node_type)?;
+
+        match (&self.index_name, &self.relation_name) {
+            (Some(index), Some(relation)) => write!(f, " using {index} on {relation}")?,
+            (Some(index), None) => write!(f, " on {index}")?,
+            (None, Some(relation)) => write!(f, " on {relation}")?,
+            (None, None) => (),
+        }
+
+        if let (Some(alias), Some(relation)) = (&self.alias, &self.relation_name)
+            && alias != relation
+        {
+            write!(f, " {alias}")?;
+        }
+
+        write!(
+            f,
+            "  (cost={:.2}..{:.2} rows={} width={})",
+            self.startup_cost, self.total_cost, self.plan_rows, self.plan_width
+        )?;
+
+        match (
+            self.actual_startup_time,
+            self.actual_total_time,
+            self.actual_rows,
+            self.actual_loops,
+        ) {
+            (_, _, _, Some(0.)) => f.write_str(" (never executed)")?,
+            (Some(startup), Some(total), Some(rows), Some(loops)) => write!(
+                f,
+                " (actual time={startup:.3}..{total:.3} rows={rows} loops={loops})"
+            )?,
+            _ => (),
+        }
+
+        writeln!(f)?;
+
+        let indent = format!("{indent}{}", if child { "      " } else { "  " });
+
+        if !self.output.is_empty() {
+            writeln!(f, "{indent}Output: {}", self.output.join(", "))?;
+        }
+
+        for key in DETAILS {
+            let value = match self.extra.get(*key) {
+                Some(serde_json::Value::String(value)) => value.clone(),
+                Some(serde_json::Value::Array(values)) => values
+                    .iter()
+                    .map(|x| {
+                        x.as_str()
+                            .map_or_else(|| x.to_string(), ToString::to_string)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                Some(value) => value.to_string(),
+                None => continue,
+            };
+
+            writeln!(f, "{indent}{key}: {value}")?;
+        }
+
+        if let Some(buffers) = &self.buffers {
+            let line = buffers.to_string();
+
+            if !line.is_empty() {
+                writeln!(f, "{indent}Buffers: {line}")?;
+            }
+        }
+
+        for plan in &self.plans {
+            plan.fmt_node(f, &indent, true)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Plan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_node(f, "", false)
+    }
+}
+
+impl std::fmt::Display for Buffers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let groups: [(&str, &[(&str, i64)]); 3] = [
+            (
+                "shared",
+                &[
+                    ("hit", self.shared_hit),
+                    ("read", self.shared_read),
+                    ("dirtied", self.shared_dirtied),
+                    ("written", self.shared_written),
+                ],
+            ),
+            (
+                "local",
+                &[
+                    ("hit", self.local_hit),
+                    ("read", self.local_read),
+                    ("dirtied", self.local_dirtied),
+                    ("written", self.local_written),
+                ],
+            ),
+            (
+                "temp",
+                &[("read", self.temp_read), ("written", self.temp_written)],
+            ),
+        ];
+
+        let mut parts = Vec::new();
+
+        for (name, counters) in groups {
+            let counters = counters
+                .iter()
+                .filter(|(_, value)| *value != 0)
+                .map(|(counter, value)| format!("{counter}={value}"))
+                .collect::<Vec<_>>();
+
+            if !counters.is_empty() {
+                parts.push(format!("{name} {}", counters.join(" ")));
+            }
+        }
+
+        f.write_str(&parts.join(", "))
+    }
+}
+
+impl std::fmt::Display for Explain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.plan.fmt(f)?;
+
+        if !self.settings.is_empty() {
+            let mut settings = self
+                .settings
+                .iter()
+                .map(|(name, value)| format!("{name} = '{value}'"))
+                .collect::<Vec<_>>();
+            settings.sort();
+
+            writeln!(f, "Settings: {}", settings.join(", "))?;
+        }
+
+        if let Some(planning_time) = self.planning_time {
+            writeln!(f, "Planning Time: {planning_time:.3} ms")?;
+        }
+
+        if let Some(execution_time) = self.execution_time {
+            writeln!(f, "Execution Time: {execution_time:.3} ms")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<serde_json::Value> for Explain {
+    type Error = crate::Error;
+
+    fn try_from(value: serde_json::Value) -> crate::Result<Self> {
+        let serde_json::Value::Array(mut values) = value else {
+            return Err(error("top level array"));
+        };
+
+        if values.is_empty() {
+            return Err(error("top level array"));
+        }
+
+        let serde_json::Value::Object(mut object) = values.swap_remove(0) else {
+            return Err(error("top level object"));
+        };
+
+        let plan = object
+            .remove("Plan")
+            .ok_or_else(|| error("Plan"))?
+            .try_into()?;
+
+        let settings = match object.remove("Settings") {
+            Some(serde_json::Value::Object(settings)) => settings
+                .into_iter()
+                .map(|(name, value)| {
+                    let value = value
+                        .as_str()
+                        .map_or_else(|| value.to_string(), ToString::to_string);
+
+                    (name, value)
+                })
+                .collect(),
+            _ => HashMap::new(),
+        };
+
+        let explain = Self {
+            plan,
+            planning_time: object
+                .get("Planning Time")
+                .and_then(serde_json::Value::as_f64),
+            execution_time: object
+                .get("Execution Time")
+                .and_then(serde_json::Value::as_f64),
+            settings,
+        };
+
+        Ok(explain)
+    }
+}
+
+impl TryFrom<serde_json::Value> for Plan {
+    type Error = crate::Error;
+
+    fn try_from(value: serde_json::Value) -> crate::Result<Self> {
+        let serde_json::Value::Object(mut object) = value else {
+            return Err(error("plan object"));
+        };
+
+        let buffers = if object.contains_key("Shared Hit Blocks") {
+            Some(Buffers {
+                shared_hit: take_i64(&mut object, "Shared Hit Blocks"),
+                shared_read: take_i64(&mut object, "Shared Read Blocks"),
+                shared_dirtied: take_i64(&mut object, "Shared Dirtied Blocks"),
+                shared_written: take_i64(&mut object, "Shared Written Blocks"),
+                local_hit: take_i64(&mut object, "Local Hit Blocks"),
+                local_read: take_i64(&mut object, "Local Read Blocks"),
+                local_dirtied: take_i64(&mut object, "Local Dirtied Blocks"),
+                local_written: take_i64(&mut object, "Local Written Blocks"),
+                temp_read: take_i64(&mut object, "Temp Read Blocks"),
+                temp_written: take_i64(&mut object, "Temp Written Blocks"),
+            })
+        } else {
+            None
+        };
+
+        let output = match object.remove("Output") {
+            Some(serde_json::Value::Array(output)) => output
+                .into_iter()
+                .filter_map(|x| x.as_str().map(ToString::to_string))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let plans = match object.remove("Plans") {
+            Some(serde_json::Value::Array(plans)) => plans
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<crate::Result<_>>()?,
+            _ => Vec::new(),
+        };
+
+        let plan = Self {
+            node_type: take_string(&mut object, "Node Type").ok_or_else(|| error("Node Type"))?,
+            relation_name: take_string(&mut object, "Relation Name"),
+            schema: take_string(&mut object, "Schema"),
+            alias: take_string(&mut object, "Alias"),
+            index_name: take_string(&mut object, "Index Name"),
+            startup_cost: take_f64(&mut object, "Startup Cost").unwrap_or_default(),
+            total_cost: take_f64(&mut object, "Total Cost").unwrap_or_default(),
+            plan_rows: take_f64(&mut object, "Plan Rows").unwrap_or_default(),
+            plan_width: take_i64(&mut object, "Plan Width"),
+            actual_startup_time: take_f64(&mut object, "Actual Startup Time"),
+            actual_total_time: take_f64(&mut object, "Actual Total Time"),
+            actual_rows: take_f64(&mut object, "Actual Rows"),
+            actual_loops: take_f64(&mut object, "Actual Loops"),
+            buffers,
+            output,
+            plans,
+            extra: object,
+        };
+
+        Ok(plan)
+    }
+}
+
+fn take_string(
+    object: &mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Option<String> {
+    match object.remove(key) {
+        Some(serde_json::Value::String(value)) => Some(value),
+        _ => None,
+    }
+}
+
+fn take_f64(object: &mut serde_json::Map<String, serde_json::Value>, key: &str) -> Option<f64> {
+    object.remove(key).and_then(|x| x.as_f64())
+}
+
+fn take_i64(object: &mut serde_json::Map<String, serde_json::Value>, key: &str) -> i64 {
+    object
+        .remove(key)
+        .and_then(|x| x.as_i64())
+        .unwrap_or_default()
+}
+
+fn error(field: &str) -> crate::Error {
+    crate::Error::Parse(format!("Invalid EXPLAIN output: missing {field}"))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn parse() -> crate::Result {
+        let json = serde_json::json!([{
+            "Plan": {
+                "Node Type": "Hash Join",
+                "Join Type": "Inner",
+                "Startup Cost": 28.01,
+                "Total Cost": 54.05,
+                "Plan Rows": 423,
+                "Plan Width": 68,
+                "Actual Startup Time": 0.003,
+                "Actual Total Time": 0.004,
+                "Actual Rows": 0,
+                "Actual Loops": 1,
+                "Hash Cond": "(t2.a = t.a)",
+                "Shared Hit Blocks": 3,
+                "Shared Read Blocks": 1,
+                "Shared Dirtied Blocks": 0,
+                "Shared Written Blocks": 0,
+                "Local Hit Blocks": 0,
+                "Local Read Blocks": 0,
+                "Local Dirtied Blocks": 0,
+                "Local Written Blocks": 0,
+                "Temp Read Blocks": 0,
+                "Temp Written Blocks": 0,
+                "Plans": [
+                    {
+                        "Node Type": "Seq Scan",
+                        "Relation Name": "t",
+                        "Schema": "public",
+                        "Alias": "t2",
+                        "Startup Cost": 0.00,
+                        "Total Cost": 22.70,
+                        "Plan Rows": 1270,
+                        "Plan Width": 36,
+                        "Actual Loops": 0,
+                    },
+                    {
+                        "Node Type": "Index Scan",
+                        "Relation Name": "t",
+                        "Index Name": "t_pkey",
+                        "Alias": "t",
+                        "Startup Cost": 0.15,
+                        "Total Cost": 8.17,
+                        "Plan Rows": 1,
+                        "Plan Width": 36,
+                        "Index Cond": "(a = 1)",
+                    },
+                ],
+            },
+            "Planning Time": 0.207,
+            "Execution Time": 0.026,
+        }]);
+
+        let explain = crate::explain::Explain::try_from(json)?;
+
+        assert_eq!(explain.plan.node_type, "Hash Join");
+        assert_eq!(explain.plan.buffers.unwrap().shared_hit, 3);
+        assert_eq!(explain.plan.extra["Join Type"], "Inner");
+        assert_eq!(explain.plan.plans.len(), 2);
+        assert_eq!(explain.plan.plans[0].relation_name.as_deref(), Some("t"));
+        assert_eq!(
+            explain
+                .plan
+                .iter()
+                .map(|x| x.node_type.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Hash Join", "Seq Scan", "Index Scan"]
+        );
+
+        assert_eq!(
+            explain.to_string(),
+            "Hash Join  (cost=28.01..54.05 rows=423 width=68) (actual time=0.003..0.004 rows=0 loops=1)
+  Hash Cond: (t2.a = t.a)
+  Buffers: shared hit=3 read=1
+  ->  Seq Scan on t t2  (cost=0.00..22.70 rows=1270 width=36) (never executed)
+  ->  Index Scan using t_pkey on t  (cost=0.15..8.17 rows=1 width=36)
+        Index Cond: (a = 1)
+Planning Time: 0.207 ms
+Execution Time: 0.026 ms
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn explain() -> crate::Result {
+        let conn = crate::test::new_conn()?;
+
+        let options = crate::explain::Options {
+            analyze: true,
+            buffers: true,
+            verbose: true,
+            ..Default::default()
+        };
+        let explain = conn.explain("select generate_series(1, $1)", &[&3], &options)?;
+
+        assert_eq!(explain.plan.node_type, "ProjectSet");
+        assert_eq!(explain.plan.actual_rows, Some(3.));
+        assert!(explain.plan.buffers.is_some());
+        assert!(explain.execution_time.is_some());
+
+        Ok(())
+    }
+}
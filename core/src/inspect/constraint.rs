@@ -22,6 +22,17 @@ pub struct Constraint {
     pub ty: Type,
     pub name: String,
     pub definition: String,
+    pub(crate) columns: Vec<String>,
+}
+
+impl Constraint {
+    /**
+     * Constrained columns, empty for a domain constraint.
+     */
+    #[must_use]
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
 }
 
 /**
@@ -34,10 +45,17 @@ pub fn constraints(
     connection
         .query(
             "
-select oid, contype as ty, conname as name, pg_get_constraintdef(oid) as definition
-    from pg_catalog.pg_constraint
-    where contypid = $1
-        or conrelid = $1;
+select c.oid, c.contype as ty, c.conname as name, pg_get_constraintdef(c.oid) as definition,
+        array(
+            select a.attname::text
+                from pg_catalog.pg_attribute a
+                where a.attrelid = c.conrelid
+                    and a.attnum = any(c.conkey)
+                order by array_position(c.conkey, a.attnum)
+        ) as columns
+    from pg_catalog.pg_constraint c
+    where c.contypid = $1
+        or c.conrelid = $1;
 ",
             &[&oid],
         )
@@ -3,6 +3,7 @@ pub mod constraint;
 mod column;
 mod extension;
 mod function;
+mod prepared_transaction;
 mod relation;
 mod schema;
 mod trigger;
@@ -12,6 +13,7 @@ pub use column::*;
 pub use constraint::{Constraint, Index, constraints, indexes};
 pub use extension::*;
 pub use function::*;
+pub use prepared_transaction::*;
 pub use relation::*;
 pub use schema::*;
 pub use trigger::*;
@@ -0,0 +1,66 @@
+#[derive(Clone, Debug, Eq, PartialEq, elephantry_derive::Entity)]
+pub struct PreparedTransaction {
+    pub transaction: u32,
+    pub gid: String,
+    /**
+     * When the transaction was prepared, as `timestamptz` text.
+     */
+    pub prepared: String,
+    pub owner: String,
+    pub database: String,
+}
+
+/**
+ * Retreive transactions currently prepared for two-phase commit, oldest
+ * first.
+ *
+ * See <https://www.postgresql.org/docs/current/view-pg-prepared-xacts.html>
+ */
+pub fn prepared_transactions(
+    connection: &crate::Connection,
+) -> crate::Result<Vec<PreparedTransaction>> {
+    connection
+        .query(
+            r#"
+select
+    p.transaction    as "transaction",
+    p.gid            as "gid",
+    p.prepared::text as "prepared",
+    p.owner          as "owner",
+    p.database       as "database"
+from pg_catalog.pg_prepared_xacts p
+order by p.prepared;
+"#,
+            &[],
+        )
+        .map(Iterator::collect)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    #[ignore = "requires max_prepared_transactions > 0"]
+    fn prepared_transactions() -> crate::Result {
+        let connection = crate::Connection::new(&crate::test::dsn())?;
+        let transaction = connection.transaction();
+        transaction.start()?;
+        let xid = connection.query_one::<i64>("select txid_current() % (1::int8 << 32)", &[])?;
+        let owner = connection.query_one::<String>("select current_user::text", &[])?;
+        transaction.prepare("elephantry-inspect-prepared")?;
+
+        let prepared = crate::inspect::prepared_transactions(&connection)?
+            .into_iter()
+            .find(|x| x.gid == "elephantry-inspect-prepared");
+        connection.rollback_prepared("elephantry-inspect-prepared")?;
+
+        let prepared = prepared.unwrap();
+        assert_eq!(i64::from(prepared.transaction), xid);
+        assert_eq!(prepared.owner, owner);
+        assert!(
+            connection
+                .query_one::<bool>("select $*::timestamptz <= now()", &[&prepared.prepared])?
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+/*!
+ * Large objects support, through the libpq large object interface.
+ *
+ * See <https://www.postgresql.org/docs/current/largeobjects.html>
+ */
+
+/**
+ * Large object access mode.
+ */
+pub type Mode = libpq::lo::Inv;
+
+/**
+ * An opened large object.
+ *
+ * Large object descriptors are only valid for the duration of a transaction:
+ * they are closed by the server at commit or rollback.
+ *
+ * ```no_run
+ * # fn main() -> elephantry::Result {
+ * use std::io::{Read, Write};
+ *
+ * let connection = elephantry::Connection::new("")?;
+ * let transaction = connection.transaction();
+ * transaction.start()?;
+ *
+ * let oid = elephantry::LargeObject::create(&transaction)?;
+ * let mut lo = elephantry::LargeObject::open(&transaction, oid, elephantry::large_object::Mode::WRITE)?;
+ * lo.write_all(b"content")?;
+ *
+ * transaction.commit()?;
+ * # Ok(())
+ * # }
+ * ```
+ */
+#[derive(Debug)]
+pub struct LargeObject<'c> {
+    connection: &'c crate::Connection,
+    oid: crate::pq::Oid,
+    fd: i32,
+}
+
+impl<'c> LargeObject<'c> {
+    /**
+     * Creates a new empty large object, returns its oid.
+     */
+    pub fn create(transaction: &crate::Transaction<'_>) -> crate::Result<crate::pq::Oid> {
+        call(
+            transaction.connection(),
+            |conn| unsafe { libpq_sys::lo_create(conn, 0) },
+            0,
+        )
+    }
+
+    /**
+     * Opens an existing large object.
+     */
+    pub fn open(
+        transaction: &crate::Transaction<'c>,
+        oid: crate::pq::Oid,
+        mode: Mode,
+    ) -> crate::Result<Self> {
+        if !transaction.is_in_transaction()? {
+            return Err(crate::Error::NotInTransaction);
+        }
+
+        let connection = transaction.connection();
+        let fd = call(
+            connection,
+            |conn| unsafe { libpq_sys::lo_open(conn, oid, mode.bits()) },
+            -1,
+        )?;
+
+        Ok(Self {
+            connection,
+            oid,
+            fd,
+        })
+    }
+
+    /**
+     * Removes a large object from the database.
+     */
+    pub fn unlink(transaction: &crate::Transaction<'_>, oid: crate::pq::Oid) -> crate::Result {
+        call(
+            transaction.connection(),
+            |conn| unsafe { libpq_sys::lo_unlink(conn, oid) },
+            -1,
+        )
+        .map(|_| ())
+    }
+
+    #[must_use]
+    pub fn oid(&self) -> crate::pq::Oid {
+        self.oid
+    }
+
+    /**
+     * Moves the current location pointer, returns the new position.
+     *
+     * As the other server errors, an invalid position aborts the
+     * transaction.
+     */
+    pub fn seek(&self, pos: std::io::SeekFrom) -> crate::Result<u64> {
+        let (offset, whence) = match pos {
+            std::io::SeekFrom::Start(offset) => (i64::try_from(offset)?, libpq::lo::Seek::Set),
+            std::io::SeekFrom::Current(offset) => (offset, libpq::lo::Seek::Cur),
+            std::io::SeekFrom::End(offset) => (offset, libpq::lo::Seek::End),
+        };
+
+        let pos = call(
+            self.connection,
+            |conn| unsafe { libpq_sys::lo_lseek64(conn, self.fd, offset, whence.into()) },
+            -1,
+        )?;
+
+        Ok(pos.try_into()?)
+    }
+
+    /**
+     * Returns the current location pointer.
+     */
+    pub fn tell(&self) -> crate::Result<u64> {
+        let pos = call(
+            self.connection,
+            |conn| unsafe { libpq_sys::lo_tell64(conn, self.fd) },
+            -1,
+        )?;
+
+        Ok(pos.try_into()?)
+    }
+
+    /**
+     * Truncates the large object to `len` bytes.
+     */
+    pub fn truncate(&self, len: u64) -> crate::Result {
+        let len = i64::try_from(len)?;
+
+        call(
+            self.connection,
+            |conn| unsafe { libpq_sys::lo_truncate64(conn, self.fd, len) },
+            -1,
+        )
+        .map(|_| ())
+    }
+
+    /**
+     * Closes the large object descriptor.
+     */
+    pub fn close(self) -> crate::Result {
+        call(
+            self.connection,
+            |conn| unsafe { libpq_sys::lo_close(conn, self.fd) },
+            -1,
+        )
+        .map(|_| ())
+    }
+}
+
+/**
+ * Calls a libpq large object function, `failed` is the value it returns on
+ * error.
+ */
+fn call<T: PartialEq>(
+    connection: &crate::Connection,
+    f: impl FnOnce(*mut libpq_sys::PGconn) -> T,
+    failed: T,
+) -> crate::Result<T> {
+    connection.exclusive.wait_drained()?;
+
+    let connection = connection
+        .connection
+        .lock()
+        .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+    let ret = f((&*connection).into());
+
+    if ret == failed {
+        let message = connection
+            .error_message()
+            .unwrap_or("Large object error")
+            .trim_end()
+            .to_string();
+
+        return Err(
+            crate::Error::Libpq(libpq::errors::Error::Backend(message)).or_lost(&connection)
+        );
+    }
+
+    Ok(ret)
+}
+
+impl std::io::Read for LargeObject<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // libpq refuses to read more than `i32::MAX` bytes at once.
+        let len = buf.len().min(i32::MAX as usize);
+
+        let read = call(
+            self.connection,
+            |conn| unsafe { libpq_sys::lo_read(conn, self.fd, buf.as_mut_ptr().cast(), len) },
+            -1,
+        )
+        .map_err(|e| io_error(e, std::io::ErrorKind::Other))?;
+
+        Ok(read as usize)
+    }
+}
+
+impl std::io::Write for LargeObject<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(i32::MAX as usize);
+
+        let written = call(
+            self.connection,
+            |conn| unsafe { libpq_sys::lo_write(conn, self.fd, buf.as_ptr().cast(), len) },
+            -1,
+        )
+        .map_err(|e| io_error(e, std::io::ErrorKind::Other))?;
+
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl std::io::Seek for LargeObject<'_> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        // The server only rejects seeks before the start or after the
+        // maximum size of a large object.
+        LargeObject::seek(self, pos).map_err(|e| io_error(e, std::io::ErrorKind::InvalidInput))
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        self.tell()
+            .map_err(|e| io_error(e, std::io::ErrorKind::Other))
+    }
+}
+
+/**
+ * Converts `error` to an IO error of `kind`, unless it's a connection or
+ * conversion error.
+ */
+fn io_error(error: crate::Error, kind: std::io::ErrorKind) -> std::io::Error {
+    let kind = match &error {
+        error if error.is_connection_error() => std::io::ErrorKind::ConnectionAborted,
+        crate::Error::TryFromIntError(_) => std::io::ErrorKind::InvalidInput,
+        crate::Error::Mutex(_) => std::io::ErrorKind::Other,
+        _ => kind,
+    };
+
+    std::io::Error::new(kind, error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Seek, Write};
+
+    #[test]
+    fn large_object() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        let transaction = conn.transaction();
+        let mode = crate::large_object::Mode::READ | crate::large_object::Mode::WRITE;
+
+        assert!(matches!(
+            crate::LargeObject::open(&transaction, 0, mode),
+            Err(crate::Error::NotInTransaction)
+        ));
+
+        transaction.start()?;
+
+        let oid = crate::LargeObject::create(&transaction)?;
+        let mut lo = crate::LargeObject::open(&transaction, oid, mode)?;
+        let content = (0..=255).collect::<Vec<u8>>();
+
+        lo.write_all(&content)?;
+        assert_eq!(lo.stream_position()?, 256);
+
+        lo.seek(std::io::SeekFrom::Start(250))?;
+        let mut buf = Vec::new();
+        lo.read_to_end(&mut buf)?;
+        assert_eq!(buf, vec![250, 251, 252, 253, 254, 255]);
+
+        let mut buf = [0; 2];
+        let err = lo.read_exact(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        let err = Seek::seek(&mut lo, std::io::SeekFrom::Start(u64::MAX)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(lo.stream_position()?, 256);
+
+        lo.truncate(10)?;
+        assert_eq!(lo.seek(std::io::SeekFrom::End(0))?, 10);
+        lo.close()?;
+
+        crate::LargeObject::unlink(&transaction, oid)?;
+        transaction.commit()?;
+
+        // A seek rejected by the server aborts the transaction.
+        transaction.start()?;
+        let oid = crate::LargeObject::create(&transaction)?;
+        let mut lo = crate::LargeObject::open(&transaction, oid, mode)?;
+        let err = Seek::seek(&mut lo, std::io::SeekFrom::Current(-1)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        transaction.roolback(None)?;
+
+        Ok(())
+    }
+}
@@ -56,17 +56,33 @@
  * | `(,)`                       | `std::ops::RangeFull`                                                                                           |                                      |
  */
 
+#[cfg(feature = "actix")]
+pub mod actix;
+pub mod advisory_lock;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "bb8")]
+pub mod bb8;
 pub mod config;
 pub mod connection;
+#[cfg(feature = "deadpool")]
+pub mod deadpool;
 pub mod entity;
+#[cfg(feature = "json")]
+pub mod explain;
 pub mod from_sql;
 #[cfg(feature = "inspect")]
 /** database inspection module. */
 pub mod inspect;
+pub mod large_object;
+pub mod lock;
 /** libpq abstraction layer. */
 pub mod pq;
+#[cfg(feature = "queue")]
+pub mod queue;
 #[cfg(feature = "r2d2")]
 pub mod r2d2;
+pub mod replication;
 #[cfg(feature = "rocket")]
 #[doc(hidden)]
 pub mod rocket;
@@ -78,18 +94,25 @@ mod r#async;
 mod errors;
 mod from_text;
 mod model;
+mod notice;
 mod notify;
 mod pager;
 mod pool;
 mod projectable;
 mod projection;
+mod replica;
 mod rows;
+#[cfg(feature = "config")]
+mod settings;
 mod sql;
 mod structure;
 mod to_text;
 mod tuple;
+#[cfg(feature = "inspect")]
+mod validation;
 
 pub use crate::config::Config;
+pub use advisory_lock::AdvisoryLock;
 pub use r#async::*;
 pub use connection::Connection;
 pub use elephantry_derive::*;
@@ -97,19 +120,27 @@ pub use entity::Entity;
 pub use errors::*;
 pub use from_sql::FromSql;
 pub use from_text::*;
+pub use large_object::LargeObject;
+pub use lock::Lock;
 pub use model::*;
+pub use notice::{Notice, NoticeHandler};
 pub use notify::Notify;
 pub use pager::*;
 pub use pool::*;
 pub use projectable::*;
 pub use projection::*;
+pub use replica::*;
 pub use rows::*;
+#[cfg(feature = "config")]
+pub use settings::*;
 pub use sql::*;
 pub use structure::*;
 pub use to_sql::ToSql;
 pub use to_text::*;
 pub use transaction::Transaction;
 pub use tuple::*;
+#[cfg(feature = "inspect")]
+pub use validation::*;
 pub use r#where::Where;
 
 macro_rules! regex {
@@ -0,0 +1,216 @@
+/**
+ * Row-level lock strength.
+ *
+ * See <https://www.postgresql.org/docs/current/explicit-locking.html#LOCKING-ROWS>
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Strength {
+    ForUpdate,
+    ForNoKeyUpdate,
+    ForShare,
+    ForKeyShare,
+}
+
+impl std::fmt::Display for Strength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::ForUpdate => "for update",
+            Self::ForNoKeyUpdate => "for no key update",
+            Self::ForShare => "for share",
+            Self::ForKeyShare => "for key share",
+        };
+
+        f.write_str(s)
+    }
+}
+
+/**
+ * What to do when a row is already locked by another transaction.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Wait {
+    /**
+     * Wait for the other transaction to release the lock. This is the
+     * default.
+     */
+    #[default]
+    Wait,
+    /**
+     * Report an error rather than waiting.
+     */
+    NoWait,
+    /**
+     * Skip rows that cannot be immediately locked.
+     */
+    SkipLocked,
+}
+
+impl std::fmt::Display for Wait {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Wait => "",
+            Self::NoWait => " nowait",
+            Self::SkipLocked => " skip locked",
+        };
+
+        f.write_str(s)
+    }
+}
+
+/**
+ * Locking clause of a SELECT statement, used by the `*_locked` finders of
+ * [`Connection`].
+ *
+ * ```
+ * let lock = elephantry::Lock::for_update()
+ *     .of("job")
+ *     .skip_locked();
+ * ```
+ *
+ * See <https://www.postgresql.org/docs/current/sql-select.html#SQL-FOR-UPDATE-SHARE>
+ *
+ * [`Connection`]: crate::Connection
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Lock {
+    pub strength: Strength,
+    pub of: Vec<String>,
+    pub wait: Wait,
+}
+
+impl Lock {
+    #[must_use]
+    pub fn new(strength: Strength) -> Self {
+        Self {
+            strength,
+            of: Vec::new(),
+            wait: Wait::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn for_update() -> Self {
+        Self::new(Strength::ForUpdate)
+    }
+
+    #[must_use]
+    pub fn for_no_key_update() -> Self {
+        Self::new(Strength::ForNoKeyUpdate)
+    }
+
+    #[must_use]
+    pub fn for_share() -> Self {
+        Self::new(Strength::ForShare)
+    }
+
+    #[must_use]
+    pub fn for_key_share() -> Self {
+        Self::new(Strength::ForKeyShare)
+    }
+
+    /**
+     * Restricts the lock to the rows coming from `relation`.
+     */
+    #[must_use]
+    pub fn of(mut self, relation: &str) -> Self {
+        self.of.push(relation.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn nowait(mut self) -> Self {
+        self.wait = Wait::NoWait;
+        self
+    }
+
+    #[must_use]
+    pub fn skip_locked(mut self) -> Self {
+        self.wait = Wait::SkipLocked;
+        self
+    }
+
+    pub(crate) fn to_clause(&self, connection: &crate::Connection) -> crate::Result<String> {
+        let mut clause = self.strength.to_string();
+
+        if !self.of.is_empty() {
+            let relations = self
+                .of
+                .iter()
+                .map(|relation| {
+                    relation
+                        .split('.')
+                        .map(|x| connection.escape_identifier(x))
+                        .collect::<crate::Result<Vec<_>>>()
+                        .map(|x| x.join("."))
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+
+            clause.push_str(&format!(" of {}", relations.join(", ")));
+        }
+
+        clause.push_str(&self.wait.to_string());
+
+        Ok(clause)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[derive(elephantry_derive::Entity)]
+    #[elephantry(model = "Model", structure = "Structure", relation = "lock_test")]
+    pub struct Entity {
+        #[elephantry(pk)]
+        pub id: i32,
+    }
+
+    #[test]
+    fn clause() -> crate::Result {
+        let conn = crate::test::new_conn()?;
+
+        assert_eq!(crate::Lock::for_update().to_clause(conn)?, "for update");
+        assert_eq!(
+            crate::Lock::for_key_share().nowait().to_clause(conn)?,
+            "for key share nowait"
+        );
+        assert_eq!(
+            crate::Lock::for_no_key_update()
+                .of("public.job")
+                .of("queue")
+                .skip_locked()
+                .to_clause(conn)?,
+            "for no key update of \"public\".\"job\", \"queue\" skip locked"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_where_locked() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        conn.execute("create temporary table lock_test as select generate_series(1, 3) as id")?;
+
+        let lock = crate::Lock::for_update().of("lock_test").skip_locked();
+
+        assert!(matches!(
+            conn.find_where_locked::<Model>("true", &[], None, &lock),
+            Err(crate::Error::NotInTransaction)
+        ));
+
+        let transaction = conn.transaction();
+        transaction.start()?;
+
+        let rows =
+            conn.find_where_locked::<Model>("id > $*", &[&1], Some("order by id limit 1"), &lock)?;
+        assert_eq!(rows.map(|x| x.id).collect::<Vec<_>>(), vec![2]);
+
+        let pager = conn.paginate_find_where_locked::<Model>("true", &[], 2, 2, None, &lock)?;
+        assert_eq!(pager.result_count(), 1);
+
+        let entity = conn.find_by_pk_locked::<Model>(&crate::pk!(id => 3), &lock)?;
+        assert_eq!(entity.map(|x| x.id), Some(3));
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+/**
+ * Notice or warning sent by the server, like the ones raised by
+ * `RAISE NOTICE`. It has the same fields as an error.
+ */
+pub type Notice = crate::DbError;
+
+/**
+ * Receiver of the notices of a connection, see
+ * [`Connection::set_notice_handler`]. Without handler, notices are logged.
+ *
+ * Implemented for closures and channel senders.
+ *
+ * [`Connection::set_notice_handler`]: crate::Connection::set_notice_handler
+ */
+pub trait NoticeHandler: Send + Sync {
+    fn notice(&self, notice: &Notice);
+}
+
+impl<F: Fn(&Notice) + Send + Sync> NoticeHandler for F {
+    fn notice(&self, notice: &Notice) {
+        self(notice);
+    }
+}
+
+impl NoticeHandler for std::sync::mpsc::Sender<Notice> {
+    fn notice(&self, notice: &Notice) {
+        self.send(notice.clone()).ok();
+    }
+}
+
+impl NoticeHandler for std::sync::mpsc::SyncSender<Notice> {
+    fn notice(&self, notice: &Notice) {
+        self.send(notice.clone()).ok();
+    }
+}
+
+/**
+ * Notices state shared by the clones of a connection.
+ */
+#[derive(Default)]
+pub(crate) struct Notices {
+    pub handler: std::sync::Mutex<Option<Box<dyn NoticeHandler>>>,
+    pub collected: std::sync::Mutex<Option<Vec<Notice>>>,
+}
+
+impl Notices {
+    fn receive(&self, notice: Notice) {
+        match self.handler.lock().as_deref() {
+            Ok(Some(handler)) => {
+                // Unwinding across the libpq callback is undefined behavior.
+                let call = std::panic::AssertUnwindSafe(|| handler.notice(&notice));
+
+                if std::panic::catch_unwind(call).is_err() {
+                    log::error!("The notice handler panicked");
+                }
+            }
+            _ => log::info!("{notice}"),
+        }
+
+        if let Ok(mut collected) = self.collected.lock()
+            && let Some(collected) = collected.as_mut()
+        {
+            collected.push(notice);
+        }
+    }
+}
+
+/**
+ * Collects the notices of a connection until dropped or finished, then
+ * restores the enclosing collection, even if the collecting code panicked.
+ */
+pub(crate) struct Collection<'n> {
+    notices: &'n Notices,
+    previous: Option<Option<Vec<Notice>>>,
+}
+
+impl<'n> Collection<'n> {
+    pub fn start(notices: &'n Notices) -> crate::Result<Self> {
+        let previous = notices
+            .collected
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .replace(Vec::new());
+
+        Ok(Self {
+            notices,
+            previous: Some(previous),
+        })
+    }
+
+    /**
+     * Returns the collected notices.
+     */
+    pub fn finish(mut self) -> crate::Result<Vec<Notice>> {
+        self.restore()
+    }
+
+    fn restore(&mut self) -> crate::Result<Vec<Notice>> {
+        let Some(previous) = self.previous.take() else {
+            return Ok(Vec::new());
+        };
+
+        let mut collected = self
+            .notices
+            .collected
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+        let notices = collected.take().unwrap_or_default();
+
+        // Nested collections also receive the notices.
+        *collected = previous.map(|mut previous| {
+            previous.extend(notices.iter().cloned());
+            previous
+        });
+
+        Ok(notices)
+    }
+}
+
+impl Drop for Collection<'_> {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
+
+impl std::fmt::Debug for Notices {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let handler = self.handler.lock().is_ok_and(|x| x.is_some());
+
+        f.debug_struct("Notices")
+            .field("handler", &handler)
+            .finish_non_exhaustive()
+    }
+}
+
+/**
+ * `arg` is the `Notices` of the connection, which lives as long as the libpq
+ * connection.
+ */
+pub(crate) extern "C" fn notice_receiver(
+    arg: *mut std::ffi::c_void,
+    result: *const libpq_sys::PGresult,
+) {
+    let notices = unsafe { &*arg.cast::<Notices>() };
+    // libpq frees the result after this call.
+    let result = std::mem::ManuallyDrop::new(libpq::Result::from(result.cast_mut()));
+
+    if let Some(notice) = Notice::from_result(&result) {
+        notices.receive(notice);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    const RAISE: &str = "do $$ begin
+    raise notice 'first';
+    raise warning 'second' using detail = 'details', hint = 'hint', errcode = 'P0042';
+end $$";
+
+    #[test]
+    fn handler() -> crate::Result {
+        let connection = crate::Connection::new(&crate::test::dsn())?;
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        connection.set_notice_handler(sender);
+        connection.execute(RAISE)?;
+
+        let notices = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(notices.len(), 2);
+        assert_eq!(notices[0].severity, "NOTICE");
+        assert_eq!(notices[0].code, "00000");
+        assert_eq!(notices[0].message, "first");
+        assert_eq!(notices[1].severity, "WARNING");
+        assert_eq!(notices[1].code, "P0042");
+        assert_eq!(notices[1].detail.as_deref(), Some("details"));
+        assert_eq!(notices[1].hint.as_deref(), Some("hint"));
+
+        let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = count.clone();
+        connection.set_notice_handler(move |_: &crate::Notice| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        });
+        connection.execute(RAISE)?;
+        assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 2);
+        assert!(receiver.try_recv().is_err());
+
+        connection.clear_notice_handler();
+        connection.execute(RAISE)?;
+        assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn collect() -> crate::Result {
+        let connection = crate::Connection::new(&crate::test::dsn())?;
+
+        let (count, notices) = connection.collect_notices(|connection| {
+            connection.execute(RAISE)?;
+            connection.query_one::<i32>("select 1", &[])
+        });
+        assert_eq!(count?, 1);
+        assert_eq!(
+            notices
+                .iter()
+                .map(|x| x.message.as_str())
+                .collect::<Vec<_>>(),
+            ["first", "second"]
+        );
+
+        // Notices raised outside of the closure aren’t collected.
+        connection.execute(RAISE)?;
+        let (result, notices) = connection.collect_notices(|_| Ok(()));
+        assert!(result.is_ok());
+        assert!(notices.is_empty());
+
+        // Notices are returned when the closure fails.
+        let (result, notices) = connection.collect_notices(|connection| {
+            connection.execute(RAISE)?;
+            connection.execute("select 1/0")
+        });
+        assert!(result.is_err());
+        assert_eq!(notices.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_panic() -> crate::Result {
+        let connection = crate::Connection::new(&crate::test::dsn())?;
+
+        let (result, notices) = connection.collect_notices(|connection| {
+            connection.execute(RAISE)?;
+
+            let nested = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                connection.collect_notices(|connection| {
+                    connection.execute(RAISE)?;
+                    panic!("collecting");
+                    #[allow(unreachable_code)]
+                    Ok(())
+                })
+            }));
+            assert!(nested.is_err());
+
+            connection.execute(RAISE)
+        });
+        assert!(result.is_ok());
+        // The enclosing collection is restored on unwind.
+        assert_eq!(notices.len(), 6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn panicking_handler() -> crate::Result {
+        let connection = crate::Connection::new(&crate::test::dsn())?;
+
+        connection.set_notice_handler(|_: &crate::Notice| panic!("handler"));
+        let (result, notices) = connection.collect_notices(|connection| connection.execute(RAISE));
+        assert!(result.is_ok());
+        assert_eq!(notices.len(), 2);
+
+        // The handler is still called.
+        let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = count.clone();
+        connection.set_notice_handler(move |_: &crate::Notice| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        });
+        connection.execute(RAISE)?;
+        assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 2);
+
+        Ok(())
+    }
+}
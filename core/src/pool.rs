@@ -1,12 +1,161 @@
 use std::collections::HashMap;
 
+/**
+ * Pool sizing and connections lifecycle.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolOptions {
+    /**
+     * Minimum number of opened connections. [`Pool`] opens them when the
+     * entry is added, idle connections are never closed below this size.
+     *
+     * The shared connection of a [`Pool`] entry is one of them.
+     */
+    pub min_size: usize,
+    /**
+     * Maximum number of opened connections.
+     */
+    pub max_size: usize,
+    /**
+     * How long to wait for a connection, `None` waits forever.
+     */
+    pub acquire_timeout: Option<std::time::Duration>,
+    /**
+     * Idle connections are closed after this duration.
+     */
+    pub idle_timeout: Option<std::time::Duration>,
+    /**
+     * Connections are closed after this duration, once returned to the
+     * pool.
+     */
+    pub max_lifetime: Option<std::time::Duration>,
+    /**
+     * Checks the connection is alive before returning it.
+     */
+    pub test_on_checkout: bool,
+    /**
+     * Initialization of each new connection.
+     */
+    pub init: crate::connection::Init,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        Self {
+            min_size: 0,
+            max_size: 10,
+            acquire_timeout: Some(std::time::Duration::from_secs(30)),
+            idle_timeout: Some(std::time::Duration::from_secs(600)),
+            max_lifetime: Some(std::time::Duration::from_secs(1800)),
+            test_on_checkout: true,
+            init: crate::connection::Init::default(),
+        }
+    }
+}
+
+impl PoolOptions {
+    pub(crate) fn is_expired(&self, created: std::time::Instant) -> bool {
+        self.max_lifetime
+            .is_some_and(|max_lifetime| created.elapsed() >= max_lifetime)
+    }
+
+    pub(crate) fn is_idle_expired(&self, since: std::time::Instant) -> bool {
+        self.idle_timeout
+            .is_some_and(|idle_timeout| since.elapsed() >= idle_timeout)
+    }
+}
+
+/**
+ * Statistics of a pool entry.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PoolStats {
+    /**
+     * Number of checked out connections.
+     */
+    pub in_use: usize,
+    /**
+     * Number of connections waiting in the pool.
+     */
+    pub idle: usize,
+    /**
+     * Number of checkouts which had to wait for a connection.
+     */
+    pub waits: u64,
+}
+
 /**
  * Connections pool.
+ *
+ * Each named entry is a pool of connections, use [`checkout`] to retreive
+ * one.
+ *
+ * For compatibility, the entry also keeps a shared connection, cloned by
+ * [`get`] and [`get_default`] (or borrowed via `Deref`). It doesn't come
+ * from the pool: all its users are serialized on it and share its session, a
+ * transaction started on it includes the queries of the other users. It's
+ * opened when the entry is added, as one of the [`PoolOptions::min_size`]
+ * connections, and isn't counted in [`PoolOptions::max_size`].
+ *
+ * [`checkout`]: #method.checkout
+ * [`get`]: #method.get
+ * [`get_default`]: #method.get_default
  */
-#[derive(Clone, Debug, Default)]
+#[derive(Debug, Default)]
 pub struct Pool {
+    entries: std::sync::RwLock<Entries>,
+    /**
+     * Shared connection of the default entry, borrowed via `Deref`.
+     * [`Pool::reload`] moves the session of a new default entry in it.
+     */
+    shared: std::sync::OnceLock<crate::Connection>,
+    #[cfg(feature = "config")]
+    settings: Option<crate::Settings>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Entries {
     default: String,
-    connections: HashMap<String, crate::Connection>,
+    entries: HashMap<String, std::sync::Arc<Entry>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Entry {
+    dsn: String,
+    options: PoolOptions,
+    /**
+     * Shared connection, replicas don't have one.
+     */
+    connection: Option<crate::Connection>,
+    state: std::sync::Mutex<State>,
+    released: std::sync::Condvar,
+    pub(crate) replication: crate::replica::Replication,
+    /**
+     * Replaced by [`Pool::reload`], returned connections are closed.
+     */
+    retired: std::sync::atomic::AtomicBool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    idle: std::collections::VecDeque<Idle>,
+    /**
+     * Number of connections in use, idle or being opened.
+     */
+    size: usize,
+    waits: u64,
+}
+
+#[derive(Debug)]
+struct Idle {
+    connection: crate::Connection,
+    created: std::time::Instant,
+    since: std::time::Instant,
+}
+
+enum Slot {
+    Idle(crate::Connection, std::time::Instant),
+    New,
 }
 
 impl Pool {
@@ -26,23 +175,217 @@ impl Pool {
         Self::default().add_default("default", &config.to_string())
     }
 
+    /**
+     * Create a pool with the named connections of `settings`, see
+     * [`Settings`].
+     *
+     * ```no_run
+     * # fn main() -> elephantry::Result {
+     * let pool = elephantry::Pool::from_settings("database.toml")?;
+     *
+     * // On SIGHUP…
+     * pool.reload()?;
+     * # Ok(())
+     * # }
+     * ```
+     *
+     * [`Settings`]: crate::Settings
+     */
+    #[cfg(feature = "config")]
+    pub fn from_settings(settings: impl Into<crate::Settings>) -> crate::Result<Self> {
+        let pool = Self {
+            settings: Some(settings.into()),
+            ..Default::default()
+        };
+        pool.reload()?;
+
+        Ok(pool)
+    }
+
+    /**
+     * Reads the settings again. Unchanged connections are kept, the others
+     * are replaced with their replicas: connections already checked out stay
+     * usable and are closed when returned, as the shared connections cloned
+     * by [`get`].
+     *
+     * The session of a new default connection replaces the one borrowed via
+     * `Deref`, this fails with [`Error::InTransaction`] if a transaction is
+     * open on it.
+     *
+     * The pool is left untouched on error.
+     *
+     * [`get`]: #method.get
+     * [`Error::InTransaction`]: crate::Error::InTransaction
+     */
+    #[cfg(feature = "config")]
+    pub fn reload(&self) -> crate::Result {
+        let Some(settings) = &self.settings else {
+            return Err(config::ConfigError::Message(
+                "This pool wasn't created from settings".to_string(),
+            )
+            .into());
+        };
+
+        let connections = settings.load()?;
+        let defaults = connections
+            .iter()
+            .filter(|(_, settings)| settings.default)
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        let default = match (defaults.as_slice(), connections.as_slice()) {
+            ([default], _) | ([], [(default, _)]) => default.clone(),
+            ([], _) => {
+                return Err(
+                    config::ConfigError::Message("No default connection".to_string()).into(),
+                );
+            }
+            _ => {
+                return Err(config::ConfigError::Message(format!(
+                    "Several default connections: {}",
+                    defaults.join(", ")
+                ))
+                .into());
+            }
+        };
+
+        let current = self.entries()?.clone();
+        let shared = self.shared.get();
+        let mut entries = HashMap::new();
+        let mut new_default = None;
+
+        for (name, settings) in &connections {
+            let dsn = settings.config.to_string();
+            let is_default = *name == default;
+            let entry = current.entries.get(name);
+
+            // The connection of the default entry is `shared`.
+            if let Some(entry) = entry
+                && entry.dsn == dsn
+                && entry.options == settings.options
+                && is_default == (*name == current.default)
+            {
+                entries.insert(name.clone(), entry.clone());
+                continue;
+            }
+
+            let last_write = shared
+                .filter(|_| is_default)
+                .and_then(|x| x.last_write.clone())
+                .unwrap_or_default();
+            let new = Entry::with_last_write(&dsn, settings.options.clone(), true, last_write)?;
+
+            if let Some(entry) = entry {
+                new.replication.keep_replicas(&entry.replication)?;
+            }
+
+            match shared {
+                Some(shared) if is_default => new_default = Some((new, shared)),
+                _ => {
+                    entries.insert(name.clone(), std::sync::Arc::new(new));
+                }
+            }
+        }
+
+        if let Some((mut entry, shared)) = new_default {
+            // The previous session is closed with `connection`.
+            let connection = entry.connection.replace(shared.clone());
+            shared.swap(connection.as_ref().unwrap())?;
+
+            entries.insert(default.clone(), std::sync::Arc::new(entry));
+        }
+
+        if shared.is_none() {
+            let _ = self
+                .shared
+                .set(entries[&default].connection.clone().unwrap());
+        }
+
+        let old = std::mem::replace(&mut *self.entries_mut()?, Entries { default, entries });
+        let new = self.entries()?;
+
+        for entry in old.entries.values() {
+            if !new
+                .entries
+                .values()
+                .any(|x| std::sync::Arc::ptr_eq(x, entry))
+            {
+                entry.retire();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entries(&self) -> crate::Result<std::sync::RwLockReadGuard<'_, Entries>> {
+        self.entries
+            .read()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))
+    }
+
+    #[cfg(feature = "config")]
+    fn entries_mut(&self) -> crate::Result<std::sync::RwLockWriteGuard<'_, Entries>> {
+        self.entries
+            .write()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))
+    }
+
+    fn default_name(&self) -> crate::Result<String> {
+        Ok(self.entries()?.default.clone())
+    }
+
     /**
      * Add a default connection.
      */
     pub fn add_default(self, name: &str, url: &str) -> crate::Result<Self> {
-        self.add(name, url, true)
+        self.add(name, url, PoolOptions::default(), true)
+    }
+
+    /**
+     * Add a default connection with custom pool options.
+     */
+    pub fn add_default_with_options(
+        self,
+        name: &str,
+        url: &str,
+        options: PoolOptions,
+    ) -> crate::Result<Self> {
+        self.add(name, url, options, true)
     }
 
     /**
      * Add a connection.
      */
     pub fn add_connection(self, name: &str, url: &str) -> crate::Result<Self> {
-        self.add(name, url, false)
+        self.add(name, url, PoolOptions::default(), false)
     }
 
-    fn add(mut self, name: &str, url: &str, default: bool) -> crate::Result<Self> {
-        self.connections
-            .insert(name.to_string(), crate::Connection::new(url)?);
+    /**
+     * Add a connection with custom pool options.
+     */
+    pub fn add_connection_with_options(
+        self,
+        name: &str,
+        url: &str,
+        options: PoolOptions,
+    ) -> crate::Result<Self> {
+        self.add(name, url, options, false)
+    }
+
+    fn add(
+        mut self,
+        name: &str,
+        url: &str,
+        options: PoolOptions,
+        default: bool,
+    ) -> crate::Result<Self> {
+        let entry = Entry::new(url, options, true)?;
+
+        self.entries
+            .get_mut()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .entries
+            .insert(name.to_string(), std::sync::Arc::new(entry));
 
         if default {
             self.set_default(name)?;
@@ -52,18 +395,25 @@ impl Pool {
     }
 
     /**
-     * Retreive the default connection.
+     * Retreive the default connection, see [`get`].
+     *
+     * [`get`]: #method.get
      */
     #[must_use]
-    pub fn get_default(&self) -> Option<&crate::Connection> {
-        self.get(&self.default)
+    pub fn get_default(&self) -> Option<crate::Connection> {
+        self.get(&self.default_name().ok()?)
     }
 
     /**
      * Set the connection `name` as default.
      */
     pub fn set_default(&mut self, name: &str) -> crate::Result {
-        if !self.connections.contains_key(name) {
+        let entries = self
+            .entries
+            .get_mut()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        if !entries.entries.contains_key(name) {
             return Err(crate::Error::Connect {
                 dsn: name.to_string(),
                 error: libpq::errors::Error::Backend(format!(
@@ -72,32 +422,531 @@ impl Pool {
             });
         }
 
-        self.default = name.to_string();
+        entries.default = name.to_string();
+        self.shared = std::sync::OnceLock::from(entries.entries[name].connection.clone().unwrap());
 
         Ok(())
     }
 
     /**
-     * Retreive the connection `name`, on `None` if not exists.
+     * Retreive a clone of the shared connection `name`, on `None` if not
+     * exists. It keeps its session until dropped, even if [`reload`]
+     * replaces the entry.
+     *
+     * This is the primary connection: after a write on it, or on a connection
+     * checked out of the entry, reads are routed to it during
+     * [`ReplicaOptions::sticky`].
      */
     #[must_use]
-    pub fn get(&self, name: &str) -> Option<&crate::Connection> {
-        self.connections.get(name)
+    pub fn get(&self, name: &str) -> Option<crate::Connection> {
+        self.find(name).ok()?.connection.clone()
+    }
+
+    /**
+     * Add a hot-standby replica to the entry `name`, with the same pool
+     * options.
+     */
+    pub fn add_replica(self, name: &str, url: &str) -> crate::Result<Self> {
+        let entry = self.find(name)?;
+        let replica = Entry::new(url, entry.options.clone(), false)?;
+
+        entry.replication.add(replica)?;
+
+        Ok(self)
+    }
+
+    /**
+     * Changes how reads are routed to the replicas of the entry `name`.
+     */
+    pub fn set_replica_options(&self, name: &str, options: crate::ReplicaOptions) -> crate::Result {
+        self.find(name)?.replication.set_options(options)
+    }
+
+    /**
+     * Retreive the default primary connection, to force a read on it. Reading
+     * doesn't route the next reads to the primary.
+     */
+    pub fn primary(&self) -> crate::Result<crate::Connection> {
+        let entry = self.find(&self.default_name()?)?;
+
+        Ok(entry.connection.clone().unwrap())
+    }
+
+    /**
+     * Retreive a connection for reads of the default entry, see
+     * [`reader_of`].
+     *
+     * [`reader_of`]: #method.reader_of
+     */
+    pub fn reader(&self) -> crate::Result<crate::Reader> {
+        self.reader_of(&self.default_name()?)
+    }
+
+    /**
+     * Retreive a connection for reads of the entry `name`: a healthy
+     * replica, or the primary connection if there is none, if a transaction
+     * is open on it or if it was written during [`ReplicaOptions::sticky`].
+     *
+     * Writes are guessed from the first keyword of the query: a `select`
+     * calling a function that modifies data, like `nextval` or
+     * `pg_advisory_lock`, is a read. Use [`primary`] for the reads that must
+     * see it.
+     *
+     * Model reads of the pool (`find_*`, `count_where`, `exist_*` and
+     * `paginate_find_where`) use it.
+     *
+     * [`primary`]: #method.primary
+     */
+    pub fn reader_of(&self, name: &str) -> crate::Result<crate::Reader> {
+        let entry = self.find(name)?;
+        let primary = entry.connection.clone().unwrap();
+
+        // The reads of a transaction must see its writes.
+        if primary.transaction_status()? != libpq::transaction::Status::Idle {
+            return Ok(crate::Reader::Primary(primary));
+        }
+
+        let reader = match entry.replication.reader()? {
+            Some(pooled) => crate::Reader::Replica(pooled),
+            None => crate::Reader::Primary(primary),
+        };
+
+        Ok(reader)
+    }
+
+    pub(crate) fn find(&self, name: &str) -> crate::Result<std::sync::Arc<Entry>> {
+        self.entries()?
+            .entries
+            .get(name)
+            .cloned()
+            .ok_or_else(|| crate::Error::Connect {
+                dsn: name.to_string(),
+                error: libpq::errors::Error::Backend(format!("Unknow {name} connection")),
+            })
+    }
+
+    /**
+     * [`Connection::find_by_pk`] on the [`reader`].
+     *
+     * [`Connection::find_by_pk`]: crate::Connection::find_by_pk
+     * [`reader`]: #method.reader
+     */
+    pub fn find_by_pk<M>(
+        &self,
+        pk: &HashMap<&str, &dyn crate::ToSql>,
+    ) -> crate::Result<Option<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        self.reader()?.find_by_pk::<M>(pk)
+    }
+
+    /**
+     * [`Connection::find_all`] on the [`reader`].
+     *
+     * [`Connection::find_all`]: crate::Connection::find_all
+     * [`reader`]: #method.reader
+     */
+    pub fn find_all<M>(&self, suffix: Option<&str>) -> crate::Result<crate::Rows<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        self.reader()?.find_all::<M>(suffix)
+    }
+
+    /**
+     * [`Connection::find_where`] on the [`reader`].
+     *
+     * [`Connection::find_where`]: crate::Connection::find_where
+     * [`reader`]: #method.reader
+     */
+    pub fn find_where<M>(
+        &self,
+        clause: &str,
+        params: &[&dyn crate::ToSql],
+        suffix: Option<&str>,
+    ) -> crate::Result<crate::Rows<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        self.reader()?.find_where::<M>(clause, params, suffix)
+    }
+
+    /**
+     * [`Connection::paginate_find_where`] on the [`reader`].
+     *
+     * [`Connection::paginate_find_where`]: crate::Connection::paginate_find_where
+     * [`reader`]: #method.reader
+     */
+    pub fn paginate_find_where<M>(
+        &self,
+        clause: &str,
+        params: &[&dyn crate::ToSql],
+        max_per_page: usize,
+        page: usize,
+        suffix: Option<&str>,
+    ) -> crate::Result<crate::Pager<M::Entity>>
+    where
+        M: crate::Model,
+    {
+        self.reader()?
+            .paginate_find_where::<M>(clause, params, max_per_page, page, suffix)
+    }
+
+    /**
+     * [`Connection::count_where`] on the [`reader`].
+     *
+     * [`Connection::count_where`]: crate::Connection::count_where
+     * [`reader`]: #method.reader
+     */
+    pub fn count_where<M>(&self, clause: &str, params: &[&dyn crate::ToSql]) -> crate::Result<usize>
+    where
+        M: crate::Model,
+    {
+        self.reader()?.count_where::<M>(clause, params)
+    }
+
+    /**
+     * [`Connection::exist_where`] on the [`reader`].
+     *
+     * [`Connection::exist_where`]: crate::Connection::exist_where
+     * [`reader`]: #method.reader
+     */
+    pub fn exist_where<M>(&self, clause: &str, params: &[&dyn crate::ToSql]) -> crate::Result<bool>
+    where
+        M: crate::Model,
+    {
+        self.reader()?.exist_where::<M>(clause, params)
+    }
+
+    /**
+     * [`Connection::exist_by_pk`] on the [`reader`].
+     *
+     * [`Connection::exist_by_pk`]: crate::Connection::exist_by_pk
+     * [`reader`]: #method.reader
+     */
+    pub fn exist_by_pk<M>(&self, pk: &HashMap<&str, &dyn crate::ToSql>) -> crate::Result<bool>
+    where
+        M: crate::Model,
+    {
+        self.reader()?.exist_by_pk::<M>(pk)
+    }
+
+    /**
+     * [`Connection::exist_one`] on the [`reader`].
+     *
+     * [`Connection::exist_one`]: crate::Connection::exist_one
+     * [`reader`]: #method.reader
+     */
+    pub fn exist_one<M>(&self, entity: &M::Entity) -> crate::Result<bool>
+    where
+        M: crate::Model,
+    {
+        self.reader()?.exist_one::<M>(entity)
+    }
+
+    /**
+     * Checks out a connection of the default entry.
+     */
+    pub fn checkout_default(&self) -> crate::Result<Pooled> {
+        self.checkout(&self.default_name()?)
+    }
+
+    /**
+     * Checks out a connection of the entry `name`, waiting up to
+     * [`PoolOptions::acquire_timeout`] if all connections are in use.
+     *
+     * The connection returns to the pool when the guard is dropped.
+     */
+    pub fn checkout(&self, name: &str) -> crate::Result<Pooled> {
+        self.find(name)?.checkout()
+    }
+
+    /**
+     * Statistics of the entry `name`.
+     */
+    pub fn stats(&self, name: &str) -> crate::Result<Option<PoolStats>> {
+        let Ok(entry) = self.find(name) else {
+            return Ok(None);
+        };
+
+        let state = entry.state()?;
+
+        let stats = PoolStats {
+            in_use: state.size - state.idle.len(),
+            idle: state.idle.len(),
+            waits: state.waits,
+        };
+
+        Ok(Some(stats))
     }
 
     /**
      * Remove the connection `name`.
      */
     pub fn remove(&mut self, name: &str) {
-        self.connections.remove(name);
+        if let Ok(entries) = self.entries.get_mut() {
+            entries.entries.remove(name);
+
+            if entries.default == name {
+                self.shared = std::sync::OnceLock::new();
+            }
+        }
+    }
+}
+
+impl Clone for Pool {
+    fn clone(&self) -> Self {
+        Self {
+            entries: std::sync::RwLock::new(
+                self.entries.read().map(|x| x.clone()).unwrap_or_default(),
+            ),
+            shared: self.shared.clone(),
+            #[cfg(feature = "config")]
+            settings: self.settings.clone(),
+        }
+    }
+}
+
+impl Entry {
+    fn new(dsn: &str, options: PoolOptions, shared: bool) -> crate::Result<Self> {
+        Self::with_last_write(dsn, options, shared, std::sync::Arc::default())
+    }
+
+    /**
+     * Creates an entry whose writes are marked in `last_write`.
+     */
+    fn with_last_write(
+        dsn: &str,
+        options: PoolOptions,
+        shared: bool,
+        last_write: std::sync::Arc<crate::replica::LastWrite>,
+    ) -> crate::Result<Self> {
+        let now = std::time::Instant::now();
+
+        let mut entry = Self {
+            dsn: dsn.to_string(),
+            options,
+            connection: None,
+            state: std::sync::Mutex::default(),
+            released: std::sync::Condvar::new(),
+            replication: crate::replica::Replication::default(),
+            retired: std::sync::atomic::AtomicBool::new(false),
+        };
+        entry.replication.last_write = last_write;
+
+        if shared {
+            entry.connection = Some(entry.connect()?);
+        }
+
+        for _ in 0..Self::min_pooled(&entry.options, shared).min(entry.options.max_size) {
+            let connection = entry.connect()?;
+            let mut state = entry.state()?;
+
+            state.idle.push_back(Idle {
+                connection,
+                created: now,
+                since: now,
+            });
+            state.size += 1;
+        }
+
+        Ok(entry)
+    }
+
+    /**
+     * Opens a new connection, its writes start the sticky window of the
+     * replicas.
+     */
+    fn connect(&self) -> crate::Result<crate::Connection> {
+        let mut connection = crate::Connection::with_init(&self.dsn, self.options.init.clone())?;
+        connection.last_write = Some(self.replication.last_write.clone());
+
+        Ok(connection)
+    }
+
+    /**
+     * Minimum number of pooled connections, the shared connection counts in
+     * [`PoolOptions::min_size`].
+     */
+    fn min_pooled(options: &PoolOptions, shared: bool) -> usize {
+        options.min_size.saturating_sub(usize::from(shared))
+    }
+
+    fn state(&self) -> crate::Result<std::sync::MutexGuard<'_, State>> {
+        self.state
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))
+    }
+
+    pub(crate) fn checkout(self: &std::sync::Arc<Self>) -> crate::Result<Pooled> {
+        let deadline = self
+            .options
+            .acquire_timeout
+            .map(|x| std::time::Instant::now() + x);
+
+        loop {
+            let slot = self.acquire(deadline)?;
+
+            // Releases the slot if the connection is unusable.
+            let mut pooled = Pooled {
+                entry: self.clone(),
+                connection: None,
+                created: std::time::Instant::now(),
+            };
+
+            match slot {
+                Slot::Idle(connection, created) => {
+                    if self.is_healthy(&connection) {
+                        pooled.connection = Some(connection);
+                        pooled.created = created;
+
+                        return Ok(pooled);
+                    }
+                }
+                Slot::New => {
+                    pooled.connection = Some(self.connect()?);
+
+                    return Ok(pooled);
+                }
+            }
+        }
+    }
+
+    fn acquire(&self, deadline: Option<std::time::Instant>) -> crate::Result<Slot> {
+        let mut state = self.state()?;
+        let mut waited = false;
+
+        loop {
+            self.reap(&mut state);
+
+            if let Some(idle) = state.idle.pop_back() {
+                return Ok(Slot::Idle(idle.connection, idle.created));
+            }
+
+            if state.size < self.options.max_size {
+                state.size += 1;
+
+                return Ok(Slot::New);
+            }
+
+            if !waited {
+                state.waits += 1;
+                waited = true;
+            }
+
+            state = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(std::time::Instant::now());
+
+                    if timeout.is_zero() {
+                        return Err(crate::Error::PoolTimeout);
+                    }
+
+                    self.released
+                        .wait_timeout(state, timeout)
+                        .map_err(|e| crate::Error::Mutex(e.to_string()))?
+                        .0
+                }
+                None => self
+                    .released
+                    .wait(state)
+                    .map_err(|e| crate::Error::Mutex(e.to_string()))?,
+            };
+        }
+    }
+
+    fn reap(&self, state: &mut State) {
+        let options = &self.options;
+        let min_size = Self::min_pooled(options, self.connection.is_some());
+        let mut size = state.size;
+
+        state.idle.retain(|x| {
+            let expired = options.is_expired(x.created)
+                || (size > min_size && options.is_idle_expired(x.since));
+
+            if expired {
+                size -= 1;
+            }
+
+            !expired
+        });
+
+        state.size = size;
+    }
+
+    fn is_healthy(&self, connection: &crate::Connection) -> bool {
+        if connection.has_broken().unwrap_or(true) {
+            return false;
+        }
+
+        !self.options.test_on_checkout || connection.execute("select 1").is_ok()
+    }
+
+    /**
+     * Closes the idle connections, and the others when they are returned.
+     */
+    #[cfg(feature = "config")]
+    fn retire(&self) {
+        self.retired
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+
+        if let Ok(mut state) = self.state() {
+            state.size -= state.idle.len();
+            state.idle.clear();
+        }
+    }
+
+    fn release(&self, connection: Option<crate::Connection>, created: std::time::Instant) {
+        let connection = connection.filter(|x| {
+            !self.retired.load(std::sync::atomic::Ordering::Relaxed)
+                && !x.has_broken().unwrap_or(true)
+                && matches!(x.transaction_status(), Ok(libpq::transaction::Status::Idle))
+                && !self.options.is_expired(created)
+        });
+
+        let Ok(mut state) = self.state() else {
+            return;
+        };
+
+        match connection {
+            Some(connection) => state.idle.push_back(Idle {
+                connection,
+                created,
+                since: std::time::Instant::now(),
+            }),
+            None => state.size -= 1,
+        }
+
+        drop(state);
+        self.released.notify_one();
     }
 }
 
-impl std::ops::Index<&str> for Pool {
-    type Output = crate::Connection;
+/**
+ * A connection checked out from [`Pool`], returned to the pool when dropped.
+ *
+ * Broken connections, connections left in a transaction or older than
+ * [`PoolOptions::max_lifetime`] are closed instead.
+ */
+#[derive(Debug)]
+pub struct Pooled {
+    entry: std::sync::Arc<Entry>,
+    connection: Option<crate::Connection>,
+    created: std::time::Instant,
+}
+
+impl std::ops::Deref for Pooled {
+    type Target = crate::Connection;
 
-    fn index(&self, index: &str) -> &Self::Output {
-        self.get(index).unwrap()
+    fn deref(&self) -> &Self::Target {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl Drop for Pooled {
+    fn drop(&mut self) {
+        self.entry.release(self.connection.take(), self.created);
     }
 }
 
@@ -105,6 +954,151 @@ impl std::ops::Deref for Pool {
     type Target = crate::Connection;
 
     fn deref(&self) -> &Self::Target {
-        self.get_default().unwrap()
+        self.shared.get().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    fn new_pool(options: crate::PoolOptions) -> crate::Result<crate::Pool> {
+        crate::Pool::default().add_default_with_options("default", &crate::test::dsn(), options)
+    }
+
+    fn options() -> crate::PoolOptions {
+        crate::PoolOptions {
+            max_size: 2,
+            acquire_timeout: Some(std::time::Duration::from_millis(100)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn checkout() -> crate::Result {
+        let pool = new_pool(options())?;
+
+        let first = pool.checkout_default()?;
+        let second = pool.checkout_default()?;
+        assert_ne!(
+            first.query_one::<i32>("select pg_backend_pid()", &[])?,
+            second.query_one::<i32>("select pg_backend_pid()", &[])?
+        );
+        assert!(matches!(
+            pool.checkout_default(),
+            Err(crate::Error::PoolTimeout)
+        ));
+
+        let pid = first.query_one::<i32>("select pg_backend_pid()", &[])?;
+        drop(first);
+
+        let third = pool.checkout_default()?;
+        assert_eq!(third.query_one::<i32>("select pg_backend_pid()", &[])?, pid);
+
+        // Broken connection
+        second.query_one::<bool>("select pg_terminate_backend($*)", &[&pid])?;
+        drop(third);
+
+        let stats = pool.stats("default")?.unwrap();
+        assert_eq!(stats.in_use, 1);
+        assert_eq!(stats.idle, 1);
+        assert_eq!(stats.waits, 1);
+
+        let fourth = pool.checkout_default()?;
+        assert_ne!(
+            fourth.query_one::<i32>("select pg_backend_pid()", &[])?,
+            pid
+        );
+        assert_eq!(pool.stats("default")?.unwrap().in_use, 2);
+
+        // The shared connection stays usable
+        assert_eq!(pool.query_one::<i32>("select 1", &[])?, 1);
+        assert!(pool.checkout("unknow").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn transaction() -> crate::Result {
+        let pool = new_pool(options())?;
+
+        let connection = pool.checkout_default()?;
+        connection.transaction().start()?;
+        drop(connection);
+
+        assert_eq!(pool.stats("default")?.unwrap().idle, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn wait() -> crate::Result {
+        let pool = new_pool(crate::PoolOptions {
+            max_size: 1,
+            acquire_timeout: None,
+            ..options()
+        })?;
+
+        let connection = pool.checkout_default()?;
+
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| pool.checkout_default().map(|_| ()));
+
+            while pool.stats("default")?.unwrap().waits == 0 {
+                std::thread::yield_now();
+            }
+            drop(connection);
+
+            waiter.join().unwrap()
+        })
+    }
+
+    #[test]
+    fn lifetime() -> crate::Result {
+        let pool = new_pool(crate::PoolOptions {
+            min_size: 2,
+            idle_timeout: Some(std::time::Duration::ZERO),
+            ..options()
+        })?;
+        assert_eq!(pool.stats("default")?.unwrap().idle, 1);
+
+        // Idle connections are kept up to the minimum size
+        drop((pool.checkout_default()?, pool.checkout_default()?));
+        drop(pool.checkout_default()?);
+        assert_eq!(pool.stats("default")?.unwrap().idle, 1);
+
+        let pool = new_pool(crate::PoolOptions {
+            max_lifetime: Some(std::time::Duration::ZERO),
+            ..options()
+        })?;
+        drop(pool.checkout_default()?);
+        assert_eq!(pool.stats("default")?.unwrap().idle, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn min_size() -> crate::Result {
+        let count = |name: &str| {
+            crate::test::new_conn()?.query_one::<i64>(
+                "select count(*) from pg_stat_activity where application_name = $*",
+                &[&name],
+            )
+        };
+
+        // The shared connection is one of the minimum connections.
+        let pool = new_pool(crate::PoolOptions {
+            min_size: 2,
+            init: crate::connection::Init::new().sql("set application_name = 'pool_min_size'"),
+            ..options()
+        })?;
+        assert_eq!(count("pool_min_size")?, 2);
+        assert_eq!(pool.stats("default")?.unwrap().idle, 1);
+
+        let _pool = new_pool(crate::PoolOptions {
+            init: crate::connection::Init::new().sql("set application_name = 'pool_no_min_size'"),
+            ..options()
+        })?;
+        assert_eq!(count("pool_no_min_size")?, 1);
+
+        Ok(())
     }
 }
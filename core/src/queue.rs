@@ -0,0 +1,595 @@
+/*!
+ * Durable job queue.
+ *
+ * Jobs are stored in the `elephantry_job` table (see [`create_schema`]) and
+ * claimed by workers with `FOR UPDATE SKIP LOCKED`, so several workers can
+ * safely process the same queue. A job is handled inside a transaction: if the
+ * worker crashes, the job becomes available again.
+ *
+ * ```no_run
+ * # fn main() -> elephantry::Result {
+ * #[derive(serde::Serialize, serde::Deserialize)]
+ * struct Email {
+ *     to: String,
+ * }
+ *
+ * let connection = elephantry::Connection::new("dbname=jobs")?;
+ * elephantry::queue::create_schema(&connection)?;
+ *
+ * let queue = elephantry::queue::Queue::new(&connection, "emails");
+ * queue.enqueue(&Email { to: "sanpi@homecomputing.fr".to_string() })?;
+ *
+ * queue.run(|job| {
+ *     let email = job.payload::<Email>()?;
+ *     // send email…
+ *     elephantry::Result::Ok(())
+ * })?;
+ * # Ok(())
+ * # }
+ * ```
+ */
+
+/**
+ * Channel used to notify workers of new jobs, the payload is the queue name.
+ */
+pub const CHANNEL: &str = "elephantry_job";
+
+const SAVEPOINT: &str = "elephantry_job";
+
+/**
+ * Create the job table, if it doesn’t already exist.
+ */
+pub fn create_schema(connection: &crate::Connection) -> crate::Result {
+    connection
+        .execute(
+            "
+create table if not exists elephantry_job (
+    id bigserial primary key,
+    queue text not null default 'default',
+    payload jsonb not null,
+    priority integer not null default 0,
+    status text not null default 'pending' check (status in ('pending', 'dead')),
+    attempts integer not null default 0,
+    max_attempts integer not null default 5,
+    last_error text,
+    run_at timestamptz not null default now(),
+    created_at timestamptz not null default now()
+);
+
+create index if not exists elephantry_job_fetch_idx
+    on elephantry_job (queue, priority desc, run_at, id)
+    where status = 'pending';
+",
+        )
+        .map(|_| ())
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, elephantry_derive::Enum)]
+pub enum Status {
+    #[elephantry(value = "pending")]
+    Pending,
+    /**
+     * The job failed `max_attempts` times and will not be retried.
+     */
+    #[elephantry(value = "dead")]
+    Dead,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, elephantry_derive::Entity)]
+#[elephantry(model = "Model", structure = "Structure", relation = "elephantry_job")]
+pub struct Job {
+    #[elephantry(pk)]
+    pub id: i64,
+    pub queue: String,
+    pub payload: crate::Jsonb,
+    pub priority: i32,
+    pub status: Status,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub last_error: Option<String>,
+}
+
+impl Job {
+    /**
+     * Deserialize the job payload.
+     */
+    pub fn payload<T: serde::de::DeserializeOwned>(&self) -> crate::Result<T> {
+        serde_json::from_value((*self.payload).clone())
+            .map_err(|e| crate::Error::Parse(format!("Invalid job payload: {e}")))
+    }
+}
+
+/**
+ * Enqueuing options.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /**
+     * Jobs with higher priority are processed first.
+     */
+    pub priority: i32,
+    /**
+     * Don’t process the job before this date. `None` means now.
+     */
+    pub run_at: Option<std::time::SystemTime>,
+    /**
+     * Override the queue [`Config::max_attempts`].
+     */
+    pub max_attempts: Option<i32>,
+}
+
+/**
+ * Exponential backoff between two attempts of a failed job.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    pub initial: std::time::Duration,
+    pub max: std::time::Duration,
+}
+
+impl Backoff {
+    /**
+     * Delay before the `attempt`th retry (starts at 1).
+     */
+    #[must_use]
+    pub fn delay(&self, attempt: i32) -> std::time::Duration {
+        let factor = 2_u32.saturating_pow(attempt.saturating_sub(1).max(0) as u32);
+
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: std::time::Duration::from_secs(1),
+            max: std::time::Duration::from_secs(3_600),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /**
+     * Default number of attempts before a job is dead-lettered.
+     */
+    pub max_attempts: i32,
+    pub backoff: Backoff,
+    /**
+     * Maximum time a worker sleeps without notification before looking for
+     * jobs, mainly to pick up delayed jobs.
+     */
+    pub poll_interval: std::time::Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            backoff: Backoff::default(),
+            poll_interval: std::time::Duration::from_secs(5),
+        }
+    }
+}
+
+/**
+ * A named queue.
+ */
+#[derive(Debug)]
+pub struct Queue<'c> {
+    connection: &'c crate::Connection,
+    name: String,
+    config: Config,
+}
+
+impl<'c> Queue<'c> {
+    #[must_use]
+    pub fn new(connection: &'c crate::Connection, name: &str) -> Self {
+        Self::with_config(connection, name, Config::default())
+    }
+
+    #[must_use]
+    pub fn with_config(connection: &'c crate::Connection, name: &str, config: Config) -> Self {
+        Self {
+            connection,
+            name: name.to_string(),
+            config,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /**
+     * Add a job to the queue with default options.
+     */
+    pub fn enqueue<T: serde::Serialize>(&self, payload: &T) -> crate::Result<Job> {
+        self.enqueue_with(payload, &Options::default())
+    }
+
+    /**
+     * Add a job to the queue.
+     *
+     * When called inside a transaction, the job is visible to workers (and
+     * they are woken up) only once the transaction is committed.
+     */
+    pub fn enqueue_with<T: serde::Serialize>(
+        &self,
+        payload: &T,
+        options: &Options,
+    ) -> crate::Result<Job> {
+        let payload = serde_json::to_value(payload)
+            .map(crate::Jsonb::from)
+            .map_err(|e| crate::Error::Parse(format!("Invalid job payload: {e}")))?;
+        let run_at = options.run_at.map(|x| {
+            x.duration_since(std::time::UNIX_EPOCH)
+                .map(|x| x.as_secs_f64())
+                .unwrap_or_default()
+        });
+        let max_attempts = options.max_attempts.unwrap_or(self.config.max_attempts);
+
+        let query = format!(
+            "insert into {} (queue, payload, priority, run_at, max_attempts)
+                values ($1, $2, $3, coalesce(to_timestamp($4), now()), $5)
+                returning {}",
+            <Structure as crate::Projectable>::relation(),
+            <Model as crate::Model>::create_projection(),
+        );
+
+        let job = self.connection.query_one::<Job>(
+            &query,
+            &[
+                &self.name,
+                &payload,
+                &options.priority,
+                &run_at,
+                &max_attempts,
+            ],
+        )?;
+
+        self.connection.notify(CHANNEL, Some(&self.name))?;
+
+        Ok(job)
+    }
+
+    /**
+     * Claim the next available job and process it with `handler`.
+     *
+     * On success the job is deleted. On error, the changes made by the
+     * handler are rollbacked, the error is recorded and the job is
+     * rescheduled according to the backoff configuration, or dead-lettered
+     * after too many attempts.
+     *
+     * The job is processed in its own transaction: returns
+     * [`Error::InTransaction`] if a transaction is already open.
+     *
+     * Returns `false` if there is no job to process.
+     *
+     * [`Error::InTransaction`]: crate::Error::InTransaction
+     */
+    pub fn process_next<F, E>(&self, handler: F) -> crate::Result<bool>
+    where
+        F: FnOnce(&Job) -> Result<(), E>,
+        E: std::fmt::Display,
+    {
+        let transaction = self.connection.transaction();
+
+        if transaction.is_in_transaction()? {
+            return Err(crate::Error::InTransaction);
+        }
+
+        transaction.start()?;
+
+        match self.process(&transaction, handler) {
+            Ok(processed) => {
+                transaction.commit()?;
+                Ok(processed)
+            }
+            Err(err) => {
+                transaction.roolback(None).ok();
+                Err(err)
+            }
+        }
+    }
+
+    fn process<F, E>(&self, transaction: &crate::Transaction<'_>, handler: F) -> crate::Result<bool>
+    where
+        F: FnOnce(&Job) -> Result<(), E>,
+        E: std::fmt::Display,
+    {
+        let lock = crate::Lock::for_update().skip_locked();
+
+        let job = self
+            .connection
+            .find_where_locked::<Model>(
+                "queue = $* and status = $* and run_at <= now()",
+                &[&self.name, &Status::Pending],
+                Some("order by priority desc, run_at, id limit 1"),
+                &lock,
+            )?
+            .next();
+
+        let Some(job) = job else {
+            return Ok(false);
+        };
+
+        transaction.set_save_point(SAVEPOINT)?;
+
+        // The handler may leave the transaction aborted, the job deletion
+        // fails in this case.
+        let result = handler(&job).map_err(|e| e.to_string()).and_then(|()| {
+            self.connection
+                .delete_one::<Model>(&job)
+                .and_then(|_| transaction.release_savepoint(SAVEPOINT))
+                .map_err(|e| e.to_string())
+        });
+
+        if let Err(err) = result {
+            transaction.roolback(Some(SAVEPOINT))?;
+            self.fail(&job, &err)?;
+        }
+
+        Ok(true)
+    }
+
+    fn fail(&self, job: &Job, error: &str) -> crate::Result {
+        let delay = self.config.backoff.delay(job.attempts + 1).as_secs_f64();
+
+        let query = format!(
+            "update {} set
+                attempts = attempts + 1,
+                last_error = $2,
+                status = case when attempts + 1 >= max_attempts then $3 else status end,
+                run_at = now() + make_interval(secs => $4)
+            where id = $1",
+            <Structure as crate::Projectable>::relation(),
+        );
+
+        self.connection
+            .query::<()>(&query, &[&job.id, &error, &Status::Dead, &delay])
+            .map(|_| ())
+    }
+
+    /**
+     * Process jobs forever, sleeping until a new job is notified when the
+     * queue is empty.
+     *
+     * Only returns on database error.
+     */
+    pub fn run<F, E>(&self, mut handler: F) -> crate::Result
+    where
+        F: FnMut(&Job) -> Result<(), E>,
+        E: std::fmt::Display,
+    {
+        self.connection.listen(CHANNEL)?;
+
+        loop {
+            while self.process_next(&mut handler)? {}
+
+            self.wait(self.config.poll_interval)?;
+        }
+    }
+
+    /**
+     * Wait for a notification on this queue, at most `timeout`.
+     *
+     * Returns `true` if a notification was received. Requires to
+     * [`listen`](crate::Connection::listen) on [`CHANNEL`]. Notifications for
+     * other channels or queues are left to
+     * [`Connection::notifies`](crate::Connection::notifies).
+     */
+    pub fn wait(&self, timeout: std::time::Duration) -> crate::Result<bool> {
+        let deadline = std::time::Instant::now() + timeout;
+
+        loop {
+            if self
+                .connection
+                .take_notifies(|x| x.relname == CHANNEL && x.extra == self.name)?
+            {
+                return Ok(true);
+            }
+
+            let now = std::time::Instant::now();
+
+            if now >= deadline || !self.wait_readable(deadline - now)? {
+                return Ok(false);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn wait_readable(&self, timeout: std::time::Duration) -> crate::Result<bool> {
+        let socket = self
+            .connection
+            .connection
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .socket()?;
+
+        let mut fd = libc::pollfd {
+            fd: socket,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+
+        match unsafe { libc::poll(&mut fd, 1, timeout) } {
+            -1 => {
+                let err = std::io::Error::last_os_error();
+
+                if err.kind() == std::io::ErrorKind::Interrupted {
+                    Ok(true)
+                } else {
+                    Err(err.into())
+                }
+            }
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn wait_readable(&self, timeout: std::time::Duration) -> crate::Result<bool> {
+        std::thread::sleep(timeout.min(std::time::Duration::from_millis(100)));
+
+        Ok(true)
+    }
+
+    /**
+     * Retreive dead-lettered jobs of this queue.
+     */
+    pub fn dead_jobs(&self) -> crate::Result<Vec<Job>> {
+        self.connection
+            .find_where::<Model>(
+                "queue = $* and status = $*",
+                &[&self.name, &Status::Dead],
+                Some("order by id"),
+            )
+            .map(Iterator::collect)
+    }
+
+    /**
+     * Put a dead-lettered job back in the queue, with its attempts counter
+     * reset.
+     */
+    pub fn retry(&self, id: i64) -> crate::Result<Option<Job>> {
+        self.connection.update_by_pk::<Model>(
+            &crate::pk!(id),
+            &crate::values!(status => Status::Pending, attempts => 0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[derive(Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct Payload {
+        n: i32,
+    }
+
+    #[test]
+    fn backoff() {
+        let backoff = super::Backoff {
+            initial: std::time::Duration::from_secs(1),
+            max: std::time::Duration::from_secs(10),
+        };
+
+        assert_eq!(backoff.delay(1).as_secs(), 1);
+        assert_eq!(backoff.delay(2).as_secs(), 2);
+        assert_eq!(backoff.delay(4).as_secs(), 8);
+        assert_eq!(backoff.delay(5).as_secs(), 10);
+        assert_eq!(backoff.delay(100).as_secs(), 10);
+    }
+
+    #[test]
+    fn queue() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        conn.execute("set search_path to pg_temp")?;
+        super::create_schema(&conn)?;
+
+        let config = super::Config {
+            max_attempts: 2,
+            backoff: super::Backoff {
+                initial: std::time::Duration::ZERO,
+                max: std::time::Duration::ZERO,
+            },
+            ..Default::default()
+        };
+        let queue = super::Queue::with_config(&conn, "test", config);
+        conn.listen(super::CHANNEL)?;
+
+        queue.enqueue(&Payload { n: 1 })?;
+        queue.enqueue_with(
+            &Payload { n: 2 },
+            &super::Options {
+                priority: 10,
+                ..Default::default()
+            },
+        )?;
+        assert!(queue.wait(std::time::Duration::from_secs(1))?);
+
+        let mut processed = Vec::new();
+        while queue.process_next(|job| {
+            processed.push(job.payload::<Payload>()?.n);
+            crate::Result::Ok(())
+        })? {}
+        assert_eq!(processed, vec![2, 1]);
+
+        queue.enqueue(&Payload { n: 3 })?;
+        while queue.process_next(|_| Err("failure"))? {}
+
+        let dead = queue.dead_jobs()?;
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 2);
+        assert_eq!(dead[0].last_error.as_deref(), Some("failure"));
+
+        let job = queue.retry(dead[0].id)?.unwrap();
+        assert_eq!(job.status, super::Status::Pending);
+        assert!(queue.process_next(|_| crate::Result::Ok(()))?);
+        assert!(queue.dead_jobs()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn aborted_handler() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        conn.execute("set search_path to pg_temp")?;
+        super::create_schema(&conn)?;
+
+        let queue = super::Queue::new(&conn, "aborted");
+        queue.enqueue(&Payload { n: 1 })?;
+
+        // The handler error is ignored but the transaction is aborted.
+        assert!(queue.process_next(|_| {
+            conn.execute("select 1/0").ok();
+            crate::Result::Ok(())
+        })?);
+
+        let job = conn.find_all::<super::Model>(None)?.next().unwrap();
+        assert_eq!(job.attempts, 1);
+        assert!(job.last_error.is_some());
+        assert_eq!(job.status, super::Status::Pending);
+
+        let transaction = conn.transaction();
+        transaction.start()?;
+        assert!(matches!(
+            queue.process_next(|_| crate::Result::Ok(())),
+            Err(crate::Error::InTransaction)
+        ));
+        transaction.roolback(None)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn wait_keeps_other_notifications() -> crate::Result {
+        let conn = crate::Connection::new(&crate::test::dsn())?;
+        conn.listen(super::CHANNEL)?;
+        conn.listen("elephantry_queue_other")?;
+
+        let queue = super::Queue::new(&conn, "wait");
+        conn.notify(super::CHANNEL, Some("another_queue"))?;
+        conn.notify("elephantry_queue_other", Some("payload"))?;
+        conn.notify(super::CHANNEL, Some("wait"))?;
+
+        assert!(queue.wait(std::time::Duration::from_secs(1))?);
+        assert!(!queue.wait(std::time::Duration::ZERO)?);
+
+        let notify = conn.notifies()?.unwrap();
+        assert_eq!(
+            (notify.relname.as_str(), notify.extra.as_str()),
+            (super::CHANNEL, "another_queue")
+        );
+        let notify = conn.notifies()?.unwrap();
+        assert_eq!(
+            (notify.relname.as_str(), notify.extra.as_str()),
+            ("elephantry_queue_other", "payload")
+        );
+        assert!(conn.notifies()?.is_none());
+
+        Ok(())
+    }
+}
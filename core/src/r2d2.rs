@@ -1,13 +1,24 @@
 pub struct ConnectionManager {
     dsn: String,
+    init: crate::connection::Init,
 }
 
 impl ConnectionManager {
     pub fn new(dsn: &str) -> Self {
         Self {
             dsn: dsn.to_string(),
+            init: crate::connection::Init::default(),
         }
     }
+
+    /**
+     * Initialization of each new connection.
+     */
+    #[must_use]
+    pub fn init(mut self, init: crate::connection::Init) -> Self {
+        self.init = init;
+        self
+    }
 }
 
 impl r2d2::ManageConnection for ConnectionManager {
@@ -15,7 +26,7 @@ impl r2d2::ManageConnection for ConnectionManager {
     type Error = crate::Error;
 
     fn connect(&self) -> std::result::Result<Self::Connection, Self::Error> {
-        crate::Connection::new(&self.dsn)
+        crate::Connection::with_init(&self.dsn, self.init.clone())
     }
 
     fn is_valid(&self, conn: &mut Self::Connection) -> std::result::Result<(), Self::Error> {
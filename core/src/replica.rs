@@ -0,0 +1,435 @@
+/**
+ * Routing of reads to hot-standby replicas.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReplicaOptions {
+    /**
+     * Replicas lagging behind the primary more than this duration are not
+     * used.
+     */
+    pub max_lag: Option<std::time::Duration>,
+    /**
+     * Delay between two health checks of a replica.
+     */
+    pub check_interval: std::time::Duration,
+    /**
+     * Reads go to the primary during this duration after a write on it, to
+     * read your own writes. Reads always go to the primary while a
+     * transaction is open on it.
+     */
+    pub sticky: Option<std::time::Duration>,
+}
+
+impl Default for ReplicaOptions {
+    fn default() -> Self {
+        Self {
+            max_lag: Some(std::time::Duration::from_secs(5)),
+            check_interval: std::time::Duration::from_secs(10),
+            sticky: Some(std::time::Duration::from_secs(5)),
+        }
+    }
+}
+
+/**
+ * Connection used for reads, see [`Pool::reader`].
+ *
+ * [`Pool::reader`]: crate::Pool::reader
+ */
+#[derive(Debug)]
+pub enum Reader {
+    Primary(crate::Connection),
+    Replica(crate::Pooled),
+}
+
+impl Reader {
+    /**
+     * Is the read routed to a replica?
+     */
+    #[must_use]
+    pub fn is_replica(&self) -> bool {
+        matches!(self, Self::Replica(_))
+    }
+}
+
+impl std::ops::Deref for Reader {
+    type Target = crate::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Primary(connection) => connection,
+            Self::Replica(pooled) => pooled,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Replication {
+    options: std::sync::RwLock<ReplicaOptions>,
+    replicas: std::sync::RwLock<Vec<std::sync::Arc<Replica>>>,
+    pub last_write: std::sync::Arc<LastWrite>,
+    next: std::sync::atomic::AtomicUsize,
+}
+
+impl Replication {
+    pub fn add(&self, replica: crate::pool::Entry) -> crate::Result {
+        self.replicas
+            .write()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .push(std::sync::Arc::new(Replica::new(replica)));
+
+        Ok(())
+    }
+
+    #[cfg(all(test, feature = "config"))]
+    pub fn count(&self) -> usize {
+        self.replicas.read().unwrap().len()
+    }
+
+    /**
+     * Keeps the replicas and options of `other`, replaced by this one.
+     */
+    #[cfg(feature = "config")]
+    pub fn keep_replicas(&self, other: &Self) -> crate::Result {
+        let replicas = other
+            .replicas
+            .read()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .clone();
+        let options = other
+            .options
+            .read()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .clone();
+
+        *self
+            .replicas
+            .write()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))? = replicas;
+
+        self.set_options(options)
+    }
+
+    pub fn set_options(&self, options: ReplicaOptions) -> crate::Result {
+        *self
+            .options
+            .write()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))? = options;
+
+        Ok(())
+    }
+
+    /**
+     * Checks out a connection of a healthy replica, in turn. Returns `None`
+     * if the primary must be used.
+     */
+    pub fn reader(&self) -> crate::Result<Option<crate::Pooled>> {
+        let options = self
+            .options
+            .read()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .clone();
+
+        if let (Some(sticky), Some(last_write)) = (options.sticky, self.last_write.get()?)
+            && last_write.elapsed() < sticky
+        {
+            return Ok(None);
+        }
+
+        let replicas = self
+            .replicas
+            .read()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?
+            .clone();
+
+        let start = self.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+        for n in 0..replicas.len() {
+            let replica = &replicas[(start + n) % replicas.len()];
+
+            if replica.is_healthy(&options)
+                && let Ok(pooled) = replica.entry.checkout()
+            {
+                return Ok(Some(pooled));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/**
+ * Time of the last write on the primary, shared by its connections.
+ */
+#[derive(Debug, Default)]
+pub(crate) struct LastWrite(std::sync::Mutex<Option<std::time::Instant>>);
+
+impl LastWrite {
+    pub fn mark(&self) {
+        if let Ok(mut last_write) = self.0.lock() {
+            *last_write = Some(std::time::Instant::now());
+        }
+    }
+
+    pub fn get(&self) -> crate::Result<Option<std::time::Instant>> {
+        self.0
+            .lock()
+            .map(|x| *x)
+            .map_err(|e| crate::Error::Mutex(e.to_string()))
+    }
+}
+
+/**
+ * Does `query` write? Guessed from its first keyword, a `with` query writes if
+ * one of its parts modifies data. A `select` is always a read, even if it calls
+ * a function that modifies data.
+ */
+pub(crate) fn is_write(query: &str) -> bool {
+    const WRITES: &[&str] = &[
+        "alter", "begin", "call", "commit", "copy", "create", "delete", "do", "drop", "end",
+        "grant", "insert", "lock", "merge", "revoke", "start", "truncate", "update",
+    ];
+
+    let query = query.trim_start().to_lowercase();
+    let keyword = query
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or_default();
+
+    if keyword == "with" {
+        query
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .any(|x| matches!(x, "insert" | "update" | "delete" | "merge"))
+    } else {
+        WRITES.contains(&keyword)
+    }
+}
+
+#[derive(Debug)]
+struct Replica {
+    entry: std::sync::Arc<crate::pool::Entry>,
+    health: std::sync::Mutex<Health>,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    healthy: bool,
+    checked: Option<std::time::Instant>,
+}
+
+impl Replica {
+    fn new(entry: crate::pool::Entry) -> Self {
+        Self {
+            entry: std::sync::Arc::new(entry),
+            health: std::sync::Mutex::default(),
+        }
+    }
+
+    fn is_healthy(&self, options: &ReplicaOptions) -> bool {
+        let Ok(mut health) = self.health.lock() else {
+            return false;
+        };
+
+        if health
+            .checked
+            .is_some_and(|x| x.elapsed() < options.check_interval)
+        {
+            return health.healthy;
+        }
+
+        health.healthy = self.check(options).unwrap_or_else(|err| {
+            log::warn!("Unable to check replica health: {err}");
+            false
+        });
+        health.checked = Some(std::time::Instant::now());
+
+        health.healthy
+    }
+
+    fn check(&self, options: &ReplicaOptions) -> crate::Result<bool> {
+        let connection = self.entry.checkout()?;
+
+        let result = connection.execute(
+            "select pg_is_in_recovery() as in_recovery,
+                case when pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() then 0
+                    else extract(epoch from now() - pg_last_xact_replay_timestamp())::float8
+                end as lag",
+        )?;
+        let tuple = result.get(0);
+
+        if !tuple.try_get::<bool>("in_recovery")? {
+            return Ok(false);
+        }
+
+        let healthy = match (options.max_lag, tuple.try_get::<Option<f64>>("lag")?) {
+            (None, _) => true,
+            (Some(max_lag), Some(lag)) => lag <= max_lag.as_secs_f64(),
+            // Nothing replayed yet
+            (Some(_), None) => false,
+        };
+
+        Ok(healthy)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[derive(Debug, elephantry_derive::Entity)]
+    #[elephantry(model = "Model", structure = "Structure", relation = "pg_database")]
+    pub struct Entity {
+        #[elephantry(pk)]
+        pub datname: String,
+    }
+
+    fn pool() -> crate::Result<crate::Pool> {
+        crate::Pool::new(&crate::test::dsn())?.add_replica("default", &crate::test::dsn())
+    }
+
+    fn set_healthy(pool: &crate::Pool) -> crate::Result {
+        let replication = &pool.find("default")?.replication;
+
+        for replica in replication.replicas.read().unwrap().iter() {
+            *replica.health.lock().unwrap() = super::Health {
+                healthy: true,
+                checked: Some(std::time::Instant::now()),
+            };
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn not_in_recovery() -> crate::Result {
+        let pool = pool()?;
+
+        // The test server is a primary
+        assert!(!pool.reader()?.is_replica());
+        assert_eq!(
+            pool.count_where::<Model>("datname = $*", &[&"postgres"])?,
+            1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn routing() -> crate::Result {
+        let pool = pool()?;
+        set_healthy(&pool)?;
+
+        let reader = pool.reader()?;
+        assert!(reader.is_replica());
+        assert_ne!(
+            reader.query_one::<i32>("select pg_backend_pid()", &[])?,
+            pool.primary()?
+                .query_one::<i32>("select pg_backend_pid()", &[])?
+        );
+        drop(reader);
+
+        // Read your writes
+        pool.execute("create temporary table if not exists routing (id int)")?;
+        assert!(!pool.reader()?.is_replica());
+
+        pool.set_replica_options(
+            "default",
+            crate::ReplicaOptions {
+                sticky: None,
+                ..Default::default()
+            },
+        )?;
+        assert!(pool.reader()?.is_replica());
+        assert!(pool.exist_where::<Model>("datname = $*", &[&"postgres"])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_are_not_sticky() -> crate::Result {
+        let pool = pool()?;
+        set_healthy(&pool)?;
+
+        pool.execute("select 1")?;
+        pool.get_default()
+            .unwrap()
+            .query_one::<i32>("select $1::int", &[&1])?;
+        pool.primary()?.execute("show server_version")?;
+        pool.checkout_default()?.execute("select 1")?;
+
+        assert!(pool.reader()?.is_replica());
+
+        Ok(())
+    }
+
+    #[test]
+    fn checkout_write_is_sticky() -> crate::Result {
+        let pool = pool()?;
+        set_healthy(&pool)?;
+
+        pool.checkout_default()?
+            .execute("create temporary table checkout_write (id int)")?;
+
+        assert!(!pool.reader()?.is_replica());
+
+        Ok(())
+    }
+
+    #[test]
+    fn shared_write_is_sticky() -> crate::Result {
+        let pool = pool()?;
+        set_healthy(&pool)?;
+
+        pool.execute("create temporary table shared_write (id int)")?;
+        *pool
+            .find("default")?
+            .replication
+            .last_write
+            .0
+            .lock()
+            .unwrap() = None;
+        assert!(pool.reader()?.is_replica());
+
+        pool.query::<i32>(
+            "with x as (insert into shared_write values ($1) returning id) select 1",
+            &[&1],
+        )?;
+        assert!(!pool.reader()?.is_replica());
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_write() {
+        assert!(super::is_write("insert into t values (1)"));
+        assert!(super::is_write("  UPDATE t set x = 1"));
+        assert!(super::is_write("begin transaction"));
+        assert!(super::is_write(
+            "with x as (delete from t returning *) select * from x"
+        ));
+        assert!(!super::is_write("select 1"));
+        assert!(!super::is_write("with x as (select 1) select * from x"));
+        assert!(!super::is_write("show server_version"));
+        // Not detected, see `Pool::reader_of`.
+        assert!(!super::is_write("select nextval('seq')"));
+    }
+
+    #[test]
+    fn transaction_is_not_routed() -> crate::Result {
+        let pool = pool()?;
+        set_healthy(&pool)?;
+        pool.set_replica_options(
+            "default",
+            crate::ReplicaOptions {
+                sticky: None,
+                ..Default::default()
+            },
+        )?;
+
+        pool.primary()?.execute("begin")?;
+        assert!(!pool.reader()?.is_replica());
+        assert!(pool.exist_where::<Model>("datname = $*", &[&"postgres"])?);
+
+        pool.primary()?.execute("commit")?;
+        assert!(pool.reader()?.is_replica());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,496 @@
+/*!
+ * Logical replication with the
+ * [pgoutput](https://www.postgresql.org/docs/current/protocol-logical-replication.html)
+ * plugin.
+ *
+ * The connection must be opened with the `replication=database` parameter.
+ *
+ * ```no_run
+ * # fn main() -> elephantry::Result {
+ * let connection = elephantry::Connection::new("dbname=app replication=database")?;
+ * connection.execute("create publication events for table event")?;
+ * let slot = connection.create_replication_slot("events", false)?;
+ *
+ * let options = elephantry::replication::Options {
+ *     publications: vec!["events".to_string()],
+ *     binary: true,
+ *     ..Default::default()
+ * };
+ * let mut stream = connection.start_replication(&slot.name, slot.consistent_point, &options)?;
+ *
+ * while let Some(message) = stream.next() {
+ *     match message? {
+ *         elephantry::replication::Message::Insert(insert) => {
+ *             let event = insert.new.tuple();
+ *             println!("new event {}", event.get::<i32>("id"));
+ *         }
+ *         elephantry::replication::Message::Commit(commit) => stream.confirm(commit.end_lsn)?,
+ *         _ => (),
+ *     }
+ * }
+ * # Ok(())
+ * # }
+ * ```
+ */
+
+mod pgoutput;
+
+pub use pgoutput::*;
+
+use crate::from_sql::{read_i64, read_u8};
+
+/**
+ * Replication slot created by [`Connection::create_replication_slot`].
+ *
+ * [`Connection::create_replication_slot`]: crate::Connection::create_replication_slot
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Slot {
+    pub name: String,
+    /**
+     * First position available for streaming.
+     */
+    pub consistent_point: crate::Lsn,
+    pub snapshot_name: Option<String>,
+    pub output_plugin: String,
+}
+
+/**
+ * Options of [`Connection::start_replication`].
+ *
+ * [`Connection::start_replication`]: crate::Connection::start_replication
+ */
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Options {
+    /**
+     * Publications whose changes are streamed.
+     */
+    pub publications: Vec<String>,
+    /**
+     * Sends the values in binary format, when the type supports it
+     * (PostgreSQL ≥ 14).
+     */
+    pub binary: bool,
+    /**
+     * Sends the messages emitted by `pg_logical_emit_message` (PostgreSQL ≥
+     * 14).
+     */
+    pub messages: bool,
+}
+
+/**
+ * Stream of replication messages created by
+ * [`Connection::start_replication`].
+ *
+ * Reading the next message blocks until the server sends data. Standby status
+ * updates are sent when the server asks for one and when a position is
+ * confirmed. Dropping the stream ends the replication.
+ *
+ * [`Connection::start_replication`]: crate::Connection::start_replication
+ */
+#[derive(Debug)]
+pub struct Stream<'c> {
+    connection: &'c crate::Connection,
+    relations: std::collections::HashMap<u32, std::sync::Arc<Relation>>,
+    received: crate::Lsn,
+    confirmed: crate::Lsn,
+    done: bool,
+}
+
+impl<'c> Stream<'c> {
+    fn new(connection: &'c crate::Connection, start: crate::Lsn) -> Self {
+        Self {
+            connection,
+            relations: std::collections::HashMap::new(),
+            received: start,
+            confirmed: crate::Lsn::default(),
+            done: false,
+        }
+    }
+
+    /**
+     * Last WAL position received.
+     */
+    #[must_use]
+    pub fn received(&self) -> crate::Lsn {
+        self.received
+    }
+
+    /**
+     * Last WAL position confirmed.
+     */
+    #[must_use]
+    pub fn confirmed(&self) -> crate::Lsn {
+        self.confirmed
+    }
+
+    /**
+     * Reports to the server that the changes up to `lsn` are processed: the
+     * slot doesn’t keep the WAL before this position anymore.
+     */
+    pub fn confirm(&mut self, lsn: crate::Lsn) -> crate::Result {
+        self.confirmed = lsn;
+
+        self.send_status()
+    }
+
+    /**
+     * Sends a standby status update.
+     */
+    fn send_status(&self) -> crate::Result {
+        let now = std::time::SystemTime::now()
+            .duration_since(epoch())
+            .unwrap_or_default();
+
+        let mut buf = Vec::with_capacity(34);
+        buf.push(b'r');
+        buf.extend_from_slice(&self.received.0.to_be_bytes());
+        buf.extend_from_slice(&self.confirmed.0.to_be_bytes());
+        buf.extend_from_slice(&self.confirmed.0.to_be_bytes());
+        buf.extend_from_slice(&(now.as_micros() as i64).to_be_bytes());
+        // Doesn’t ask for a reply.
+        buf.push(0);
+
+        let connection = self
+            .connection
+            .connection
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        connection.put_copy_data(&buf).map_err(crate::Error::Copy)?;
+        connection.flush().map_err(crate::Error::Copy)?;
+
+        Ok(())
+    }
+
+    /**
+     * Reads the next CopyData message, `None` at the end of the COPY.
+     */
+    fn copy_data(&self) -> crate::Result<Option<Vec<u8>>> {
+        let connection = self
+            .connection
+            .connection
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        let mut ptr = std::ptr::null_mut();
+        let len = unsafe { libpq_sys::PQgetCopyData((&*connection).into(), &mut ptr, 0) };
+
+        match len {
+            -1 => Ok(None),
+            len if len < 0 => Err(crate::Error::Copy(libpq::errors::Error::Backend(
+                connection.error_message().unwrap_or_default().to_string(),
+            ))),
+            len => {
+                let data =
+                    unsafe { std::slice::from_raw_parts(ptr.cast::<u8>(), len as usize) }.to_vec();
+                unsafe { libpq_sys::PQfreemem(ptr.cast()) };
+
+                Ok(Some(data))
+            }
+        }
+    }
+
+    /**
+     * Retreives the result of the `START_REPLICATION` command.
+     */
+    fn finish(&mut self) -> crate::Result {
+        self.done = true;
+
+        let connection = self
+            .connection
+            .connection
+            .lock()
+            .map_err(|e| crate::Error::Mutex(e.to_string()))?;
+
+        let mut error = None;
+
+        while let Some(result) = connection.result() {
+            if let Err(err) = crate::pq::Result::try_from(result) {
+                error.get_or_insert(err);
+            }
+        }
+
+        error.map_or(Ok(()), Err)
+    }
+
+    fn read(&mut self) -> crate::Result<Option<Message>> {
+        loop {
+            let Some(data) = self.copy_data()? else {
+                self.finish()?;
+
+                return Ok(None);
+            };
+
+            let buf = &mut data.as_slice();
+
+            match read_u8(buf)? {
+                // XLogData
+                b'w' => {
+                    let start = pgoutput::read_lsn(buf)?;
+                    read_i64(buf)?;
+                    read_i64(buf)?;
+
+                    self.received = self.received.max(start);
+
+                    return pgoutput::decode(buf, &mut self.relations).map(Some);
+                }
+                // Primary keepalive
+                b'k' => {
+                    let end = pgoutput::read_lsn(buf)?;
+                    read_i64(buf)?;
+                    let reply = read_u8(buf)? == 1;
+
+                    self.received = self.received.max(end);
+
+                    if reply {
+                        self.send_status()?;
+                    }
+                }
+                c => {
+                    return Err(crate::Error::Replication(format!(
+                        "unknown message type '{}'",
+                        c as char
+                    )));
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Stream<'_> {
+    type Item = crate::Result<Message>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        self.read().transpose()
+    }
+}
+
+impl Drop for Stream<'_> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        // The server answers to the end of COPY by ending its own COPY.
+        if let Ok(connection) = self.connection.connection.lock() {
+            connection.put_copy_end(None).ok();
+        }
+
+        while let Ok(Some(_)) = self.copy_data() {}
+
+        self.finish().ok();
+    }
+}
+
+/**
+ * Origin of the replication timestamps.
+ */
+pub(crate) fn epoch() -> std::time::SystemTime {
+    std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(946_684_800)
+}
+
+pub(crate) fn create_slot(
+    connection: &crate::Connection,
+    name: &str,
+    temporary: bool,
+) -> crate::Result<Slot> {
+    let query = format!(
+        "CREATE_REPLICATION_SLOT {}{} LOGICAL pgoutput NOEXPORT_SNAPSHOT",
+        connection.escape_identifier(name)?,
+        if temporary { " TEMPORARY" } else { "" },
+    );
+
+    let result = connection.execute(&query)?;
+    let tuple = result
+        .try_get(0)
+        .ok_or_else(|| crate::Error::MissingField("slot_name".to_string()))?;
+
+    Ok(Slot {
+        name: tuple.try_get("slot_name")?,
+        consistent_point: tuple.try_get::<String>("consistent_point")?.parse()?,
+        snapshot_name: tuple.try_get("snapshot_name")?,
+        output_plugin: tuple.try_get("output_plugin")?,
+    })
+}
+
+pub(crate) fn drop_slot(connection: &crate::Connection, name: &str) -> crate::Result {
+    let query = format!(
+        "DROP_REPLICATION_SLOT {} WAIT",
+        connection.escape_identifier(name)?
+    );
+
+    connection.execute(&query).map(|_| ())
+}
+
+pub(crate) fn start<'c>(
+    connection: &'c crate::Connection,
+    slot: &str,
+    start: crate::Lsn,
+    options: &Options,
+) -> crate::Result<Stream<'c>> {
+    let publications = options
+        .publications
+        .iter()
+        .map(|x| connection.escape_identifier(x))
+        .collect::<crate::Result<Vec<_>>>()?
+        .join(",");
+
+    let mut plugin_options = vec![
+        "proto_version '1'".to_string(),
+        format!(
+            "publication_names {}",
+            connection.escape_literal(&publications)?
+        ),
+    ];
+    if options.binary {
+        plugin_options.push("binary 'true'".to_string());
+    }
+    if options.messages {
+        plugin_options.push("messages 'true'".to_string());
+    }
+
+    let query = format!(
+        "START_REPLICATION SLOT {} LOGICAL {start} ({})",
+        connection.escape_identifier(slot)?,
+        plugin_options.join(", "),
+    );
+
+    connection.execute(&query)?;
+
+    Ok(Stream::new(connection, start))
+}
+
+#[cfg(test)]
+mod test {
+    #[derive(Debug, PartialEq, elephantry_derive::Entity)]
+    struct Event {
+        id: i32,
+        name: String,
+        payload: Option<crate::Bytea>,
+    }
+
+    fn replication(binary: bool) -> crate::Result {
+        use super::Message;
+
+        let mut config = crate::test::dsn().parse::<crate::Config>()?;
+        config.replication = Some("database".to_string());
+        let connection = crate::Connection::from_config(&config)?;
+
+        let table = format!("replication_{binary}");
+        connection.execute(&format!(
+            "drop table if exists {table} cascade;
+            create table {table} (id int primary key, name text not null, payload bytea);
+            alter table {table} alter payload set storage external;
+            drop publication if exists {table};
+            create publication {table} for table {table};"
+        ))?;
+
+        let slot = connection.create_replication_slot(&table, true)?;
+        assert_eq!(slot.output_plugin, "pgoutput");
+
+        let writer = crate::test::new_conn()?;
+        writer.execute(&format!(
+            "insert into {table} values (1, 'first', '\\x0102');
+            update {table} set name = 'updated' where id = 1;
+            update {table} set payload = decode(repeat('ff', 10000), 'hex') where id = 1;
+            update {table} set name = 'toasted' where id = 1;
+            delete from {table} where id = 1;"
+        ))?;
+
+        let options = super::Options {
+            publications: vec![table.clone()],
+            binary,
+            ..Default::default()
+        };
+        let mut stream =
+            connection.start_replication(&slot.name, slot.consistent_point, &options)?;
+
+        assert!(matches!(stream.next(), Some(Ok(Message::Begin(_)))));
+        assert!(
+            matches!(stream.next(), Some(Ok(Message::Relation(relation))) if relation.name == table)
+        );
+
+        let Some(Ok(Message::Insert(insert))) = stream.next() else {
+            panic!("insert expected");
+        };
+        assert_eq!(
+            insert.new.entity::<Event>()?,
+            Event {
+                id: 1,
+                name: "first".to_string(),
+                payload: Some(crate::Bytea::from(vec![1, 2])),
+            }
+        );
+
+        let Some(Ok(Message::Update(update))) = stream.next() else {
+            panic!("update expected");
+        };
+        assert!(update.old.is_none());
+        assert_eq!(update.new.entity::<Event>()?.name, "updated");
+
+        let Some(Ok(Message::Update(update))) = stream.next() else {
+            panic!("update expected");
+        };
+        assert_eq!(
+            update.new.entity::<Event>()?.payload.map(|x| x.len()),
+            Some(10_000)
+        );
+
+        // The TOASTed payload isn’t sent again.
+        let Some(Ok(Message::Update(update))) = stream.next() else {
+            panic!("update expected");
+        };
+        assert_eq!(update.new.values[2], super::Value::Unchanged);
+        assert_eq!(update.new.tuple().try_get::<String>("name")?, "toasted");
+        assert!(matches!(
+            update.new.entity::<Event>(),
+            Err(crate::Error::Unchanged(column)) if column == "payload"
+        ));
+
+        let Some(Ok(Message::Delete(delete))) = stream.next() else {
+            panic!("delete expected");
+        };
+        assert_eq!(delete.old.tuple().get::<i32>("id"), 1);
+
+        let Some(Ok(Message::Commit(commit))) = stream.next() else {
+            panic!("commit expected");
+        };
+        stream.confirm(commit.end_lsn)?;
+        assert_eq!(stream.confirmed(), commit.end_lsn);
+
+        drop(stream);
+
+        connection.drop_replication_slot(&slot.name)?;
+        writer.execute(&format!(
+            "drop publication {table}; drop table {table} cascade;"
+        ))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn text() -> crate::Result {
+        replication(false)
+    }
+
+    #[test]
+    fn binary() -> crate::Result {
+        replication(true)
+    }
+
+    #[test]
+    fn lsn() -> crate::Result {
+        let lsn = "16/B374D848".parse::<crate::Lsn>()?;
+
+        assert_eq!(lsn, crate::Lsn(0x16_B374_D848));
+        assert_eq!(lsn.to_string(), "16/B374D848");
+        assert!("16".parse::<crate::Lsn>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,418 @@
+use crate::from_sql::{read_i16, read_i32, read_i64, read_u8, read_u32};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/**
+ * Logical replication message, see
+ * <https://www.postgresql.org/docs/current/protocol-logicalrep-message-formats.html>
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Begin(Begin),
+    Commit(Commit),
+    Origin(Origin),
+    /**
+     * Description of a table, sent before the first change of this table and
+     * each time its definition changes.
+     */
+    Relation(Arc<Relation>),
+    Type(Type),
+    Insert(Insert),
+    Update(Update),
+    Delete(Delete),
+    Truncate(Truncate),
+    /**
+     * Message emitted by `pg_logical_emit_message`, see
+     * [`Options::messages`].
+     *
+     * [`Options::messages`]: super::Options::messages
+     */
+    Message(LogicalMessage),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Begin {
+    /** LSN of the commit record of the transaction. */
+    pub final_lsn: crate::Lsn,
+    pub timestamp: std::time::SystemTime,
+    pub xid: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Commit {
+    pub commit_lsn: crate::Lsn,
+    /** End of the transaction, the position to confirm. */
+    pub end_lsn: crate::Lsn,
+    pub timestamp: std::time::SystemTime,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Origin {
+    pub commit_lsn: crate::Lsn,
+    pub name: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReplicaIdentity {
+    Default,
+    Nothing,
+    Full,
+    Index,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Relation {
+    pub id: u32,
+    pub namespace: String,
+    pub name: String,
+    pub replica_identity: ReplicaIdentity,
+    pub columns: Vec<Column>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Column {
+    /** Is the column part of the replica identity? */
+    pub key: bool,
+    pub name: String,
+    pub type_id: libpq::Oid,
+    pub type_modifier: i32,
+}
+
+/**
+ * Description of a custom type used by a relation.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Type {
+    pub id: libpq::Oid,
+    pub namespace: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Insert {
+    pub new: Row,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Update {
+    /**
+     * Previous values of the replica identity columns, or of the whole row
+     * with `REPLICA IDENTITY FULL`. Only sent if they changed or with
+     * `REPLICA IDENTITY FULL`.
+     */
+    pub old: Option<Row>,
+    pub new: Row,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delete {
+    /**
+     * Values of the replica identity columns, or of the whole row with
+     * `REPLICA IDENTITY FULL`.
+     */
+    pub old: Row,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Truncate {
+    pub relations: Vec<Arc<Relation>>,
+    pub cascade: bool,
+    pub restart_identity: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LogicalMessage {
+    pub transactional: bool,
+    pub lsn: crate::Lsn,
+    pub prefix: String,
+    pub content: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Value {
+    Null,
+    /**
+     * TOASTed value not sent because it didn’t change, decoding it fails with
+     * [`Error::Unchanged`].
+     *
+     * [`Error::Unchanged`]: crate::Error::Unchanged
+     */
+    Unchanged,
+    Text(Vec<u8>),
+    Binary(Vec<u8>),
+}
+
+/**
+ * Values of a row of a relation.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub relation: Arc<Relation>,
+    pub values: Vec<Value>,
+}
+
+impl Row {
+    #[must_use]
+    pub fn tuple(&self) -> crate::Tuple<'_> {
+        crate::Tuple::from_replication(self)
+    }
+
+    /**
+     * Decodes the row into an entity, fails if a column it reads is
+     * [`Value::Unchanged`].
+     */
+    pub fn entity<E: crate::Entity>(&self) -> crate::Result<E> {
+        E::try_from(&self.tuple())
+    }
+
+    pub(crate) fn value(&self, n: usize) -> crate::Result<(crate::pq::Format, Option<&[u8]>)> {
+        let value = match self.values.get(n) {
+            Some(Value::Text(value)) => (crate::pq::Format::Text, Some(value.as_slice())),
+            Some(Value::Binary(value)) => (crate::pq::Format::Binary, Some(value.as_slice())),
+            Some(Value::Unchanged) => {
+                let column = self
+                    .relation
+                    .columns
+                    .get(n)
+                    .map(|x| x.name.clone())
+                    .unwrap_or_default();
+
+                return Err(crate::Error::Unchanged(column));
+            }
+            Some(Value::Null) | None => (crate::pq::Format::Binary, None),
+        };
+
+        Ok(value)
+    }
+}
+
+/**
+ * Decodes a pgoutput message, `relations` keeps the relations received so far.
+ */
+pub(crate) fn decode(
+    buf: &mut &[u8],
+    relations: &mut HashMap<u32, Arc<Relation>>,
+) -> crate::Result<Message> {
+    let message = match read_u8(buf)? {
+        b'B' => Message::Begin(Begin {
+            final_lsn: read_lsn(buf)?,
+            timestamp: read_timestamp(buf)?,
+            xid: read_u32(buf)?,
+        }),
+        b'C' => {
+            read_u8(buf)?;
+
+            Message::Commit(Commit {
+                commit_lsn: read_lsn(buf)?,
+                end_lsn: read_lsn(buf)?,
+                timestamp: read_timestamp(buf)?,
+            })
+        }
+        b'O' => Message::Origin(Origin {
+            commit_lsn: read_lsn(buf)?,
+            name: read_string(buf)?,
+        }),
+        b'R' => {
+            let id = read_u32(buf)?;
+            let namespace = read_string(buf)?;
+            let name = read_string(buf)?;
+            let replica_identity = match read_u8(buf)? {
+                b'd' => ReplicaIdentity::Default,
+                b'n' => ReplicaIdentity::Nothing,
+                b'f' => ReplicaIdentity::Full,
+                b'i' => ReplicaIdentity::Index,
+                c => return Err(error(format!("unknown replica identity '{}'", c as char))),
+            };
+
+            let mut columns = Vec::new();
+            for _ in 0..read_i16(buf)? {
+                columns.push(Column {
+                    key: read_u8(buf)? & 1 == 1,
+                    name: read_string(buf)?,
+                    type_id: read_u32(buf)?,
+                    type_modifier: read_i32(buf)?,
+                });
+            }
+
+            let relation = Arc::new(Relation {
+                id,
+                namespace,
+                name,
+                replica_identity,
+                columns,
+            });
+            relations.insert(id, relation.clone());
+
+            Message::Relation(relation)
+        }
+        b'Y' => Message::Type(Type {
+            id: read_u32(buf)?,
+            namespace: read_string(buf)?,
+            name: read_string(buf)?,
+        }),
+        b'I' => {
+            let relation = read_relation(buf, relations)?;
+            expect(buf, b'N')?;
+
+            Message::Insert(Insert {
+                new: read_row(buf, relation)?,
+            })
+        }
+        b'U' => {
+            let relation = read_relation(buf, relations)?;
+
+            let old = match read_u8(buf)? {
+                b'K' | b'O' => {
+                    let old = read_row(buf, relation.clone())?;
+                    expect(buf, b'N')?;
+
+                    Some(old)
+                }
+                b'N' => None,
+                c => return Err(error(format!("unexpected tuple type '{}'", c as char))),
+            };
+
+            Message::Update(Update {
+                old,
+                new: read_row(buf, relation)?,
+            })
+        }
+        b'D' => {
+            let relation = read_relation(buf, relations)?;
+
+            match read_u8(buf)? {
+                b'K' | b'O' => (),
+                c => return Err(error(format!("unexpected tuple type '{}'", c as char))),
+            }
+
+            Message::Delete(Delete {
+                old: read_row(buf, relation)?,
+            })
+        }
+        b'T' => {
+            let count = read_u32(buf)?;
+            let options = read_u8(buf)?;
+
+            let mut truncated = Vec::new();
+            for _ in 0..count {
+                truncated.push(read_relation(buf, relations)?);
+            }
+
+            Message::Truncate(Truncate {
+                relations: truncated,
+                cascade: options & 1 == 1,
+                restart_identity: options & 2 == 2,
+            })
+        }
+        b'M' => {
+            let transactional = read_u8(buf)? == 1;
+            let lsn = read_lsn(buf)?;
+            let prefix = read_string(buf)?;
+            let len = read_u32(buf)? as usize;
+
+            Message::Message(LogicalMessage {
+                transactional,
+                lsn,
+                prefix,
+                content: read_bytes(buf, len)?.to_vec(),
+            })
+        }
+        c => return Err(error(format!("unknown message type '{}'", c as char))),
+    };
+
+    Ok(message)
+}
+
+pub(crate) fn read_lsn(buf: &mut &[u8]) -> crate::Result<crate::Lsn> {
+    read_i64(buf).map(|x| crate::Lsn(x as u64))
+}
+
+/**
+ * Reads a timestamp, in microseconds since 2000-01-01.
+ */
+pub(crate) fn read_timestamp(buf: &mut &[u8]) -> crate::Result<std::time::SystemTime> {
+    let micros = read_i64(buf)?;
+    let duration = std::time::Duration::from_micros(micros.unsigned_abs());
+
+    let timestamp = if micros < 0 {
+        super::epoch() - duration
+    } else {
+        super::epoch() + duration
+    };
+
+    Ok(timestamp)
+}
+
+fn read_string(buf: &mut &[u8]) -> crate::Result<String> {
+    let Some(len) = buf.iter().position(|x| *x == 0) else {
+        return Err(error("unterminated string".to_string()));
+    };
+
+    let s = String::from_utf8(buf[..len].to_vec())?;
+    *buf = &buf[len + 1..];
+
+    Ok(s)
+}
+
+fn read_bytes<'a>(buf: &mut &'a [u8], len: usize) -> crate::Result<&'a [u8]> {
+    if buf.len() < len {
+        return Err(error("truncated message".to_string()));
+    }
+
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+
+    Ok(bytes)
+}
+
+fn read_relation(
+    buf: &mut &[u8],
+    relations: &HashMap<u32, Arc<Relation>>,
+) -> crate::Result<Arc<Relation>> {
+    let id = read_u32(buf)?;
+
+    relations
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| error(format!("unknown relation {id}")))
+}
+
+fn read_row(buf: &mut &[u8], relation: Arc<Relation>) -> crate::Result<Row> {
+    let mut values = Vec::new();
+
+    for _ in 0..read_i16(buf)? {
+        let value = match read_u8(buf)? {
+            b'n' => Value::Null,
+            b'u' => Value::Unchanged,
+            kind @ (b't' | b'b') => {
+                let len = read_u32(buf)? as usize;
+                let data = read_bytes(buf, len)?.to_vec();
+
+                if kind == b't' {
+                    Value::Text(data)
+                } else {
+                    Value::Binary(data)
+                }
+            }
+            c => return Err(error(format!("unknown value kind '{}'", c as char))),
+        };
+
+        values.push(value);
+    }
+
+    Ok(Row { relation, values })
+}
+
+fn expect(buf: &mut &[u8], expected: u8) -> crate::Result {
+    match read_u8(buf)? {
+        c if c == expected => Ok(()),
+        c => Err(error(format!(
+            "expected '{}', found '{}'",
+            expected as char, c as char
+        ))),
+    }
+}
+
+fn error(message: String) -> crate::Error {
+    crate::Error::Replication(message)
+}
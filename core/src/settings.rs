@@ -0,0 +1,328 @@
+/**
+ * Source of the named connections of [`Pool::from_settings`], read again by
+ * [`Pool::reload`].
+ *
+ * The settings are a table of connections, each one a table of libpq
+ * parameters (see [`Config`]) plus these optional keys:
+ *
+ * - `default`: this connection is the default one;
+ * - `min_size`, `max_size` and `test_on_checkout`: see [`PoolOptions`];
+ * - `acquire_timeout`, `idle_timeout` and `max_lifetime`: see
+ *   [`PoolOptions`], in seconds;
+ * - `init`: SQL statements run on each new connection, see [`Init`].
+ *
+ * ```toml
+ * [main]
+ * default = true
+ * host = "localhost"
+ * dbname = "app"
+ * max_size = 20
+ * init = ["set search_path to app", "set timezone to 'UTC'"]
+ *
+ * [reporting]
+ * host = "reporting.local"
+ * dbname = "app"
+ * sslmode = "verify-full"
+ * ```
+ *
+ * [`Config`]: crate::Config
+ * [`Init`]: crate::connection::Init
+ * [`Pool::from_settings`]: crate::Pool::from_settings
+ * [`Pool::reload`]: crate::Pool::reload
+ * [`PoolOptions`]: crate::PoolOptions
+ */
+#[derive(Clone, Debug)]
+pub enum Settings {
+    /** File whose format is guessed from its extension (TOML, YAML…). */
+    File(std::path::PathBuf),
+    /** Sources of the `config` crate, built on each load. */
+    Builder(config::ConfigBuilder<config::builder::DefaultState>),
+}
+
+impl Settings {
+    pub(crate) fn load(&self) -> crate::Result<Vec<(String, ConnectionSettings)>> {
+        let builder = match self {
+            Self::File(path) => {
+                config::Config::builder().add_source(config::File::from(path.as_path()))
+            }
+            Self::Builder(builder) => builder.clone(),
+        };
+
+        let mut connections = builder
+            .build()?
+            .try_deserialize::<std::collections::HashMap<String, config::Value>>()?
+            .into_iter()
+            .map(|(name, value)| {
+                let settings = ConnectionSettings::try_from(value)?;
+
+                Ok((name, settings))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        connections.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(connections)
+    }
+}
+
+impl From<&str> for Settings {
+    fn from(path: &str) -> Self {
+        Self::File(path.into())
+    }
+}
+
+impl From<&std::path::Path> for Settings {
+    fn from(path: &std::path::Path) -> Self {
+        Self::File(path.to_path_buf())
+    }
+}
+
+impl From<std::path::PathBuf> for Settings {
+    fn from(path: std::path::PathBuf) -> Self {
+        Self::File(path)
+    }
+}
+
+impl From<config::ConfigBuilder<config::builder::DefaultState>> for Settings {
+    fn from(builder: config::ConfigBuilder<config::builder::DefaultState>) -> Self {
+        Self::Builder(builder)
+    }
+}
+
+/**
+ * A built configuration doesn't change when reloaded.
+ */
+impl From<config::Config> for Settings {
+    fn from(config: config::Config) -> Self {
+        Self::Builder(config::Config::builder().add_source(config))
+    }
+}
+
+/**
+ * Settings of a named connection.
+ */
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct ConnectionSettings {
+    pub config: crate::Config,
+    pub options: crate::PoolOptions,
+    pub default: bool,
+}
+
+impl TryFrom<config::Value> for ConnectionSettings {
+    type Error = crate::Error;
+
+    fn try_from(value: config::Value) -> crate::Result<Self> {
+        let seconds = |value: config::Value| {
+            value
+                .into_uint()
+                .map(|x| Some(std::time::Duration::from_secs(x)))
+        };
+
+        let mut settings = Self::default();
+
+        for (key, value) in value.into_table()? {
+            match key.as_str() {
+                "default" => settings.default = value.into_bool()?,
+                "min_size" => settings.options.min_size = value.into_uint()?.try_into()?,
+                "max_size" => settings.options.max_size = value.into_uint()?.try_into()?,
+                "acquire_timeout" => settings.options.acquire_timeout = seconds(value)?,
+                "idle_timeout" => settings.options.idle_timeout = seconds(value)?,
+                "max_lifetime" => settings.options.max_lifetime = seconds(value)?,
+                "test_on_checkout" => settings.options.test_on_checkout = value.into_bool()?,
+                "init" => {
+                    for query in value.into_array()? {
+                        settings.options.init = settings.options.init.sql(&query.into_string()?);
+                    }
+                }
+                _ => {
+                    let value = match value.kind {
+                        config::ValueKind::Boolean(value) => i32::from(value).to_string(),
+                        kind => kind.to_string(),
+                    };
+
+                    settings.config.try_set(&key, &value)?;
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    fn write(path: &std::path::Path, application_name: &str, max_size: usize) -> crate::Result {
+        let dsn = crate::test::dsn().parse::<crate::Config>()?;
+        let host = dsn.host.unwrap_or_else(|| "localhost".to_string());
+        let user = dsn.user.unwrap_or_else(|| "postgres".to_string());
+
+        std::fs::write(
+            path,
+            format!(
+                r#"
+[main]
+default = true
+host = "{host}"
+user = "{user}"
+dbname = "postgres"
+max_size = {max_size}
+keepalives = true
+init = ["set application_name = '{application_name}'"]
+
+[other]
+host = "{host}"
+user = "{user}"
+dbname = "postgres"
+application_name = "other app"
+acquire_timeout = 5
+"#
+            ),
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_settings() -> crate::Result {
+        let path = std::env::temp_dir().join(format!("elephantry-{}.toml", std::process::id()));
+        write(&path, "main app", 2)?;
+
+        let pool = std::sync::Arc::new(
+            crate::Pool::from_settings(path.as_path())?.add_replica("main", &crate::test::dsn())?,
+        );
+        assert_eq!(
+            pool.get_default()
+                .unwrap()
+                .query_one::<String>("show application_name", &[])?,
+            "main app"
+        );
+        assert_eq!(
+            pool.get("other")
+                .unwrap()
+                .query_one::<String>("show application_name", &[])?,
+            "other app"
+        );
+
+        let checkout = pool.checkout_default()?;
+        let shared = pool.get_default().unwrap();
+        let replaced = shared.query_one::<i32>("select pg_backend_pid()", &[])?;
+
+        // A transaction on the shared connection can't lose its session.
+        write(&path, "reloaded app", 3)?;
+        pool.execute("begin")?;
+        assert!(matches!(pool.reload(), Err(crate::Error::InTransaction)));
+        pool.execute("commit")?;
+        assert_eq!(
+            pool.query_one::<i32>("select pg_backend_pid()", &[])?,
+            replaced
+        );
+
+        // Only the changed connection is replaced, with its replicas.
+        let other = pool
+            .get("other")
+            .unwrap()
+            .query_one::<i32>("select pg_backend_pid()", &[])?;
+        std::thread::spawn({
+            let pool = pool.clone();
+            move || pool.reload()
+        })
+        .join()
+        .unwrap()?;
+
+        assert_eq!(
+            pool.get_default()
+                .unwrap()
+                .query_one::<String>("show application_name", &[])?,
+            "reloaded app"
+        );
+        assert_eq!(pool.find("main")?.replication.count(), 1);
+        assert_eq!(
+            pool.get("other")
+                .unwrap()
+                .query_one::<i32>("select pg_backend_pid()", &[])?,
+            other
+        );
+
+        assert_eq!(
+            pool.query_one::<String>("show application_name", &[])?,
+            "reloaded app"
+        );
+
+        // In-flight checkouts keep working, the shared connection follows
+        // the reload and its previous session is closed.
+        assert_eq!(
+            checkout.query_one::<String>("show application_name", &[])?,
+            "main app"
+        );
+        drop(checkout);
+        assert_eq!(
+            shared.query_one::<String>("show application_name", &[])?,
+            "reloaded app"
+        );
+        assert_eq!(pool.stats("main")?.unwrap().in_use, 0);
+
+        let mut closed = false;
+
+        for _ in 0..50 {
+            closed = !pool.query_one::<bool>(
+                "select exists(select from pg_stat_activity where pid = $*)",
+                &[&replaced],
+            )?;
+
+            if closed {
+                break;
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        assert!(closed);
+
+        // A broken file keeps the current connections.
+        std::fs::write(&path, "[main]\nsslmode = \"invalid\"\n")?;
+        assert!(pool.reload().is_err());
+        assert!(pool.get("other").is_some());
+
+        std::fs::remove_file(&path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn env() -> crate::Result {
+        let source = config::Environment::default()
+            .prefix("ELEPHANTRY")
+            .prefix_separator("_")
+            .separator("__")
+            .source(Some(std::collections::HashMap::from([
+                ("ELEPHANTRY_MAIN__HOST".to_string(), "localhost".to_string()),
+                (
+                    "ELEPHANTRY_MAIN__SSLMODE".to_string(),
+                    "verify-full".to_string(),
+                ),
+                (
+                    "ELEPHANTRY_MAIN__CONNECT_TIMEOUT".to_string(),
+                    "10".to_string(),
+                ),
+                ("ELEPHANTRY_MAIN__MAX_SIZE".to_string(), "5".to_string()),
+                ("ELEPHANTRY_MAIN__DEFAULT".to_string(), "true".to_string()),
+            ])));
+
+        let settings = crate::Settings::from(config::Config::builder().add_source(source));
+        let connections = settings.load()?;
+
+        assert_eq!(connections.len(), 1);
+        let (name, main) = &connections[0];
+        assert_eq!(name, "main");
+        assert!(main.default);
+        assert_eq!(main.options.max_size, 5);
+        assert_eq!(main.config.connect_timeout, Some(10));
+        assert!(matches!(
+            main.config.sslmode,
+            Some(crate::config::SslMode::VerifyFull)
+        ));
+
+        Ok(())
+    }
+}
@@ -80,7 +80,10 @@ impl crate::ToSql for Bytea {
      * https://github.com/postgres/postgres/blob/REL_12_0/src/backend/utils/adt/varlena.c#L277
      */
     fn to_text(&self) -> crate::Result<Option<String>> {
-        let data = String::from_utf8(self.0.clone())?;
+        let data = self.0.iter().fold("\\x".to_string(), |mut data, byte| {
+            data.push_str(&format!("{byte:02x}"));
+            data
+        });
 
         Ok(Some(data))
     }
@@ -124,6 +127,7 @@ mod test {
         let tests = [
             ("'abcd'", crate::Bytea::from(Vec::from("abcd"))),
             ("'\\x123456'", crate::Bytea::from(vec![0x12, 0x34, 0x56])),
+            ("'\\xff00'", crate::Bytea::from(vec![0xff, 0x00])),
         ];
 
         let conn = crate::test::new_conn()?;
@@ -0,0 +1,98 @@
+/**
+ * Rust type for [pg_lsn](https://www.postgresql.org/docs/current/datatype-pg-lsn.html),
+ * a position in the write-ahead log.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Lsn(pub u64);
+
+impl From<u64> for Lsn {
+    fn from(lsn: u64) -> Self {
+        Self(lsn)
+    }
+}
+
+impl From<Lsn> for u64 {
+    fn from(lsn: Lsn) -> Self {
+        lsn.0
+    }
+}
+
+impl std::fmt::Display for Lsn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:X}/{:X}", self.0 >> 32, self.0 & 0xFFFF_FFFF)
+    }
+}
+
+impl std::str::FromStr for Lsn {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let Some((hi, lo)) = s.split_once('/') else {
+            return Err(crate::Error::Parse(format!("Invalid LSN '{s}'")));
+        };
+
+        let hi = u64::from_str_radix(hi, 16)?;
+        let lo = u64::from_str_radix(lo, 16)?;
+
+        if hi > 0xFFFF_FFFF || lo > 0xFFFF_FFFF {
+            return Err(crate::Error::Parse(format!("Invalid LSN '{s}'")));
+        }
+
+        Ok(Self(hi << 32 | lo))
+    }
+}
+
+impl crate::ToSql for Lsn {
+    fn ty(&self) -> crate::pq::Type {
+        crate::pq::types::PG_LSN
+    }
+
+    /*
+     * https://github.com/postgres/postgres/blob/REL_12_0/src/backend/utils/adt/pg_lsn.c#L68
+     */
+    fn to_text(&self) -> crate::Result<Option<String>> {
+        self.to_string().to_text()
+    }
+
+    /*
+     * https://github.com/postgres/postgres/blob/REL_12_0/src/backend/utils/adt/pg_lsn.c#L98
+     */
+    fn to_binary(&self) -> crate::Result<Option<Vec<u8>>> {
+        Ok(Some(self.0.to_be_bytes().to_vec()))
+    }
+}
+
+impl crate::FromSql for Lsn {
+    /*
+     * https://github.com/postgres/postgres/blob/REL_12_0/src/backend/utils/adt/pg_lsn.c#L55
+     */
+    fn from_text(ty: &crate::pq::Type, raw: Option<&str>) -> crate::Result<Self> {
+        crate::from_sql::not_null(raw)?
+            .parse()
+            .map_err(|_| Self::error(ty, raw))
+    }
+
+    /*
+     * https://github.com/postgres/postgres/blob/REL_12_0/src/backend/utils/adt/pg_lsn.c#L84
+     */
+    fn from_binary(ty: &crate::pq::Type, raw: Option<&[u8]>) -> crate::Result<Self> {
+        let lsn = i64::from_binary(ty, raw)?;
+
+        Ok(Self(lsn as u64))
+    }
+}
+
+impl crate::entity::Simple for Lsn {}
+
+#[cfg(test)]
+mod test {
+    crate::sql_test!(
+        pg_lsn,
+        crate::Lsn,
+        [
+            ("'0/0'", crate::Lsn(0)),
+            ("'16/B374D848'", crate::Lsn(0x16_B374_D848)),
+            ("'FFFFFFFF/FFFFFFFF'", crate::Lsn(u64::MAX)),
+        ]
+    );
+}
@@ -14,6 +14,7 @@ mod hstore;
 mod json;
 #[cfg(feature = "json")]
 mod jsonb;
+mod lsn;
 #[cfg(feature = "ltree")]
 mod ltree;
 #[cfg(feature = "money")]
@@ -47,6 +48,7 @@ pub use hstore::*;
 pub use json::*;
 #[cfg(feature = "json")]
 pub use jsonb::*;
+pub use lsn::*;
 #[cfg(feature = "ltree")]
 pub use ltree::*;
 #[cfg(feature = "money")]
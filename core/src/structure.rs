@@ -4,4 +4,9 @@
 pub trait Structure: crate::Projectable {
     /** Get the list of column contitutes the primary key. */
     fn primary_key() -> &'static [&'static str];
+
+    /** Get the name of the entity field stored in `column`. */
+    fn field(column: &str) -> Option<&'static str> {
+        Self::columns().iter().find(|x| **x == column).copied()
+    }
 }
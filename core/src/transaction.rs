@@ -91,6 +91,10 @@ impl<'c> Transaction<'c> {
         Self { connection }
     }
 
+    pub(crate) fn connection(&self) -> &'c crate::Connection {
+        self.connection
+    }
+
     /**
      * Start a new transaction.
      */
@@ -105,49 +109,54 @@ impl<'c> Transaction<'c> {
         self.exec("commit transaction")
     }
 
+    /**
+     * Prepare the current transaction for two-phase commit. After this
+     * command, the transaction is no longer associated with the current
+     * session, use [`Connection::commit_prepared`] or
+     * [`Connection::rollback_prepared`] to finish it.
+     *
+     * See <https://www.postgresql.org/docs/current/sql-prepare-transaction.html>
+     *
+     * [`Connection::commit_prepared`]: crate::Connection::commit_prepared
+     * [`Connection::rollback_prepared`]: crate::Connection::rollback_prepared
+     */
+    pub fn prepare(&self, gid: &str) -> crate::Result {
+        let query = format!(
+            "prepare transaction {}",
+            self.connection.escape_literal(gid)?
+        );
+
+        self.exec(&query)
+    }
+
     /**
      * Rollback a transaction. If a `name` is specified, the transaction is
      * rollback to the given savepoint. Otherwise, the whole transaction is
      * rollback.
      */
     pub fn roolback(&self, name: Option<&str>) -> crate::Result {
-        let query = match name {
-            Some(name) => format!("rollback to savepoint {name}"),
-            None => "rollback transaction".to_string(),
-        };
-
-        self.exec(&query)
+        self.exec(&rollback_query(name))
     }
 
     /**
      * Set a savepoint in a transaction.
      */
     pub fn set_save_point(&self, name: &str) -> crate::Result {
-        let query = format!("savepoint {name}");
-
-        self.exec(&query)
+        self.exec(&savepoint_query(name))
     }
 
     /**
      * Drop a savepoint.
      */
     pub fn release_savepoint(&self, name: &str) -> crate::Result {
-        let query = format!("release savepoint {name}");
-
-        self.exec(&query)
+        self.exec(&release_savepoint_query(name))
     }
 
     /**
      * Tell if a transaction is open or not.
      */
     pub fn is_in_transaction(&self) -> crate::Result<bool> {
-        let status = self.connection.transaction_status()?;
-
-        let in_transaction = status == libpq::transaction::Status::Active
-            || status == libpq::transaction::Status::InTrans
-            || status == libpq::transaction::Status::InError;
-
-        Ok(in_transaction)
+        is_in_transaction(self.connection)
     }
 
     /**
@@ -156,13 +165,7 @@ impl<'c> Transaction<'c> {
      * transaction's status. If no transactions are open, it returns `None`.
      */
     pub fn is_transaction_ok(&self) -> crate::Result<Option<bool>> {
-        if !self.is_in_transaction()? {
-            return Ok(None);
-        }
-
-        let status = self.connection.transaction_status()?;
-
-        Ok(Some(status == libpq::transaction::Status::InTrans))
+        is_transaction_ok(self.connection)
     }
 
     /**
@@ -177,25 +180,7 @@ impl<'c> Transaction<'c> {
         keys: Option<Vec<&str>>,
         constraints: Constraints,
     ) -> crate::Result {
-        let name = if let Some(keys) = keys {
-            keys.iter()
-                .map(|key| self.escape_identifier(key))
-                .collect::<crate::Result<Vec<_>>>()?
-                .join(", ")
-        } else {
-            "ALL".to_string()
-        };
-
-        let query = format!("set constraints {name} {constraints}");
-
-        self.exec(&query)
-    }
-
-    fn escape_identifier(&self, id: &str) -> crate::Result<String> {
-        id.split('.')
-            .map(|x| self.connection.escape_identifier(x))
-            .collect::<crate::Result<Vec<_>>>()
-            .map(|x| x.join("."))
+        self.exec(&deferrable_query(self.connection, keys, constraints)?)
     }
 
     /**
@@ -205,9 +190,7 @@ impl<'c> Transaction<'c> {
      * See <http://www.postgresql.org/docs/current/sql-set-transaction.html>
      */
     pub fn set_isolation_level(&self, level: IsolationLevel) -> crate::Result {
-        let query = format!("set transaction isolation level {level}");
-
-        self.exec(&query)
+        self.exec(&isolation_level_query(level))
     }
 
     /**
@@ -217,12 +200,77 @@ impl<'c> Transaction<'c> {
      * See <http://www.postgresql.org/docs/current/sql-set-transaction.html>
      */
     pub fn set_access_mode(&self, mode: AccessMode) -> crate::Result {
-        let query = format!("set transaction {mode}");
-
-        self.exec(&query)
+        self.exec(&access_mode_query(mode))
     }
 
     fn exec(&self, query: &str) -> crate::Result {
         self.connection.execute(query).map(|_| ())
     }
 }
+
+pub(crate) fn rollback_query(name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("rollback to savepoint {name}"),
+        None => "rollback transaction".to_string(),
+    }
+}
+
+pub(crate) fn savepoint_query(name: &str) -> String {
+    format!("savepoint {name}")
+}
+
+pub(crate) fn release_savepoint_query(name: &str) -> String {
+    format!("release savepoint {name}")
+}
+
+pub(crate) fn deferrable_query(
+    connection: &crate::Connection,
+    keys: Option<Vec<&str>>,
+    constraints: Constraints,
+) -> crate::Result<String> {
+    let name = if let Some(keys) = keys {
+        keys.iter()
+            .map(|key| escape_identifier(connection, key))
+            .collect::<crate::Result<Vec<_>>>()?
+            .join(", ")
+    } else {
+        "ALL".to_string()
+    };
+
+    Ok(format!("set constraints {name} {constraints}"))
+}
+
+fn escape_identifier(connection: &crate::Connection, id: &str) -> crate::Result<String> {
+    id.split('.')
+        .map(|x| connection.escape_identifier(x))
+        .collect::<crate::Result<Vec<_>>>()
+        .map(|x| x.join("."))
+}
+
+pub(crate) fn isolation_level_query(level: IsolationLevel) -> String {
+    format!("set transaction isolation level {level}")
+}
+
+pub(crate) fn access_mode_query(mode: AccessMode) -> String {
+    format!("set transaction {mode}")
+}
+
+pub(crate) fn is_in_transaction(connection: &crate::Connection) -> crate::Result<bool> {
+    let status = connection.transaction_status()?;
+
+    let in_transaction = status == libpq::transaction::Status::Active
+        || status == libpq::transaction::Status::InTrans
+        || status == libpq::transaction::Status::InError;
+
+    Ok(in_transaction)
+}
+
+pub(crate) fn is_transaction_ok(connection: &crate::Connection) -> crate::Result<Option<bool>> {
+    if !is_in_transaction(connection)? {
+        return Ok(None);
+    }
+
+    let status = connection.transaction_status()?;
+
+    Ok(Some(status == libpq::transaction::Status::InTrans))
+}
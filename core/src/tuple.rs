@@ -3,13 +3,42 @@
  */
 #[derive(Clone, Debug)]
 pub struct Tuple<'a> {
-    result: &'a libpq::Result,
-    index: usize,
+    source: Source<'a>,
+}
+
+#[derive(Clone, Debug)]
+enum Source<'a> {
+    Result {
+        result: &'a libpq::Result,
+        index: usize,
+    },
+    /**
+     * Binary values decoded from a COPY, `result` only describes the fields.
+     */
+    Copy {
+        result: &'a libpq::Result,
+        values: &'a [Option<Vec<u8>>],
+    },
+    Replication(&'a crate::replication::Row),
 }
 
 impl<'a> Tuple<'a> {
     pub(crate) fn from(result: &'a libpq::Result, index: usize) -> Self {
-        Self { result, index }
+        Self {
+            source: Source::Result { result, index },
+        }
+    }
+
+    pub(crate) fn from_copy(result: &'a libpq::Result, values: &'a [Option<Vec<u8>>]) -> Self {
+        Self {
+            source: Source::Copy { result, values },
+        }
+    }
+
+    pub(crate) fn from_replication(row: &'a crate::replication::Row) -> Self {
+        Self {
+            source: Source::Replication(row),
+        }
     }
 
     /**
@@ -36,7 +65,7 @@ impl<'a> Tuple<'a> {
     where
         T: crate::FromSql,
     {
-        let n = match self.result.field_number(name) {
+        let n = match self.field_number(name) {
             Some(n) => n,
             None => return Err(crate::Error::MissingField(name.to_string())),
         };
@@ -69,8 +98,15 @@ impl<'a> Tuple<'a> {
         T: crate::FromSql,
     {
         let ty = self.field_type(n);
-        let format = self.result.field_format(n);
-        let value = self.result.value(self.index, n);
+
+        let (format, value) = match &self.source {
+            Source::Result { result, index } => (result.field_format(n), result.value(*index, n)),
+            Source::Copy { values, .. } => (
+                crate::pq::Format::Binary,
+                values.get(n).and_then(Option::as_deref),
+            ),
+            Source::Replication(row) => row.value(n)?,
+        };
 
         crate::FromSql::from_sql(&ty, format, value)
     }
@@ -80,7 +116,10 @@ impl<'a> Tuple<'a> {
      */
     #[must_use]
     pub fn len(&self) -> usize {
-        self.result.nfields()
+        match &self.source {
+            Source::Result { result, .. } | Source::Copy { result, .. } => result.nfields(),
+            Source::Replication(row) => row.relation.columns.len(),
+        }
     }
 
     /**
@@ -96,11 +135,28 @@ impl<'a> Tuple<'a> {
      * than or equal to the length of the tuple.
      */
     pub fn field_name(&self, n: usize) -> crate::Result<Option<String>> {
-        Ok(self.result.field_name(n)?)
+        match &self.source {
+            Source::Result { result, .. } | Source::Copy { result, .. } => {
+                Ok(result.field_name(n)?)
+            }
+            Source::Replication(row) => Ok(row.relation.columns.get(n).map(|x| x.name.clone())),
+        }
+    }
+
+    fn field_number(&self, name: &str) -> Option<usize> {
+        match &self.source {
+            Source::Result { result, .. } | Source::Copy { result, .. } => {
+                result.field_number(name)
+            }
+            Source::Replication(row) => row.relation.columns.iter().position(|x| x.name == name),
+        }
     }
 
     fn field_type(&self, n: usize) -> crate::pq::Type {
-        let oid = self.result.field_type(n);
+        let oid = match &self.source {
+            Source::Result { result, .. } | Source::Copy { result, .. } => result.field_type(n),
+            Source::Replication(row) => row.relation.columns.get(n).map_or(0, |x| x.type_id),
+        };
 
         match crate::pq::Type::try_from(oid) {
             Ok(ty) => ty,
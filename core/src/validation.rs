@@ -0,0 +1,286 @@
+/**
+ * Kind of violated constraint.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ValidationKind {
+    Check,
+    Exclusion,
+    ForeignKey,
+    NotNull,
+    Unique,
+}
+
+impl ValidationKind {
+    fn from_code(code: &str) -> Option<Self> {
+        let kind = match code {
+            "23502" => Self::NotNull,
+            "23503" => Self::ForeignKey,
+            "23505" => Self::Unique,
+            "23514" => Self::Check,
+            "23P01" => Self::Exclusion,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+}
+
+impl std::fmt::Display for ValidationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Check => "check",
+            Self::Exclusion => "exclusion",
+            Self::ForeignKey => "foreign_key",
+            Self::NotNull => "not_null",
+            Self::Unique => "unique",
+        };
+
+        f.write_str(s)
+    }
+}
+
+/**
+ * Entity field involved in a constraint violation.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ValidationError {
+    pub field: String,
+    pub kind: ValidationKind,
+    pub constraint: Option<String>,
+}
+
+/**
+ * Constraint violation translated to entity fields by
+ * [`Connection::validation_errors`].
+ *
+ * [`Connection::validation_errors`]: crate::Connection::validation_errors
+ */
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl std::ops::Deref for ValidationErrors {
+    type Target = [ValidationError];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors = self
+            .iter()
+            .map(|x| format!("{}: {}", x.field, x.kind))
+            .collect::<Vec<_>>();
+
+        f.write_str(&errors.join(", "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+#[derive(elephantry_derive::Entity)]
+struct Relation {
+    oid: crate::pq::Oid,
+    schema: String,
+    name: String,
+}
+
+pub(crate) fn validation_errors<M>(
+    connection: &crate::Connection,
+    error: &crate::Error,
+) -> crate::Result<Option<ValidationErrors>>
+where
+    M: crate::Model,
+{
+    use crate::Structure as _;
+
+    let Some(db_error) = error.db_error() else {
+        return Ok(None);
+    };
+
+    let Some(kind) = ValidationKind::from_code(&db_error.code) else {
+        return Ok(None);
+    };
+
+    let relation = connection.query_one::<Relation>(
+        "
+select c.oid, n.nspname::text as schema, c.relname::text as name
+    from pg_catalog.pg_class c
+    join pg_catalog.pg_namespace n on n.oid = c.relnamespace
+    where c.oid = $1::regclass
+",
+        &[&<M::Structure as crate::Projectable>::relation()],
+    )?;
+
+    // The violated constraint belongs to another relation.
+    if db_error.table.as_ref() != Some(&relation.name)
+        || db_error.schema.as_ref() != Some(&relation.schema)
+    {
+        return Ok(None);
+    }
+
+    let columns = match (&kind, &db_error.constraint) {
+        (ValidationKind::NotNull, _) => db_error.column.iter().cloned().collect(),
+        (_, Some(name)) => match crate::inspect::constraints(connection, relation.oid)?
+            .into_iter()
+            .find(|x| &x.name == name)
+        {
+            Some(constraint) => constraint.columns,
+            // Unique index created without constraint.
+            None => connection.query_one::<Vec<String>>(
+                "
+select array(
+        select a.attname::text
+            from pg_catalog.pg_attribute a
+            where a.attrelid = i.indrelid
+                and a.attnum = any(i.indkey)
+            order by array_position(i.indkey::int2[], a.attnum)
+    )
+    from pg_catalog.pg_index i
+    join pg_catalog.pg_class c on c.oid = i.indexrelid
+    where i.indrelid = $1
+        and c.relname = $2
+",
+                &[&relation.oid, name],
+            )?,
+        },
+        (_, None) => Vec::new(),
+    };
+
+    let errors = columns
+        .iter()
+        .filter_map(|column| M::Structure::field(column))
+        .map(|field| ValidationError {
+            field: field.to_string(),
+            kind,
+            constraint: db_error.constraint.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(ValidationErrors(errors)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[derive(Clone, Debug, Default, elephantry_derive::Entity)]
+    #[elephantry(model = "Model", structure = "Structure", relation = "validation_user")]
+    struct User {
+        #[elephantry(pk)]
+        id: i32,
+        #[elephantry(column = "mail")]
+        email: Option<String>,
+        age: i32,
+        team_id: Option<i32>,
+        #[elephantry(column = "kind")]
+        r#type: Option<String>,
+    }
+
+    #[test]
+    fn field() {
+        use crate::Structure as _;
+
+        assert_eq!(Structure::field("mail"), Some("email"));
+        assert_eq!(Structure::field("kind"), Some("type"));
+        assert_eq!(Structure::field("unknown"), None);
+    }
+
+    #[test]
+    fn validation_errors() -> crate::Result {
+        use crate::{ValidationError, ValidationErrors, ValidationKind};
+
+        let connection = crate::Connection::new(&crate::test::dsn())?;
+        connection.execute(
+            "
+create temporary table validation_team (id int primary key);
+create temporary table validation_user (
+    id int primary key,
+    mail text,
+    age int not null constraint validation_user_age check (age >= 0),
+    team_id int constraint validation_user_team references validation_team,
+    kind text
+);
+create unique index validation_user_mail on validation_user (mail);
+insert into validation_user values (1, 'jdoe@example.org', 20, null);
+",
+        )?;
+
+        let errors = |user: User| -> crate::Result<Option<ValidationErrors>> {
+            let err = connection.insert_one::<Model>(&user).unwrap_err();
+
+            connection.validation_errors::<Model>(&err)
+        };
+
+        let user = User {
+            id: 2,
+            email: Some("jdoe@example.org".to_string()),
+            age: 20,
+            ..Default::default()
+        };
+        assert_eq!(
+            errors(user)?,
+            Some(ValidationErrors(vec![ValidationError {
+                field: "email".to_string(),
+                kind: ValidationKind::Unique,
+                constraint: Some("validation_user_mail".to_string()),
+            }]))
+        );
+
+        let errors_of = |user: User| -> crate::Result<Vec<(String, ValidationKind)>> {
+            Ok(errors(user)?
+                .unwrap_or_default()
+                .iter()
+                .map(|x| (x.field.clone(), x.kind))
+                .collect())
+        };
+
+        assert_eq!(
+            errors_of(User {
+                id: 1,
+                ..Default::default()
+            })?,
+            vec![("id".to_string(), ValidationKind::Unique)]
+        );
+        assert_eq!(
+            errors_of(User {
+                id: 2,
+                age: -1,
+                ..Default::default()
+            })?,
+            vec![("age".to_string(), ValidationKind::Check)]
+        );
+        assert_eq!(
+            errors_of(User {
+                id: 2,
+                team_id: Some(1),
+                ..Default::default()
+            })?,
+            vec![("team_id".to_string(), ValidationKind::ForeignKey)]
+        );
+
+        let err = connection
+            .execute("insert into validation_user (id) values (2)")
+            .unwrap_err();
+        assert_eq!(
+            connection
+                .validation_errors::<Model>(&err)?
+                .unwrap()
+                .to_string(),
+            "age: not_null"
+        );
+
+        let err = connection.execute("select 1/0").unwrap_err();
+        assert!(connection.validation_errors::<Model>(&err)?.is_none());
+
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use darling::{FromDeriveInput, FromField};
+use syn::ext::IdentExt;
 
 pub(crate) fn impl_macro(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let params = crate::params::Entity::from_derive_input(ast)?;
@@ -42,6 +43,7 @@ fn entity_impl(
     }
 
     let mut from_body = Vec::new();
+    let mut try_from_body = Vec::new();
     let mut get_body = Vec::new();
 
     for field in fields {
@@ -78,6 +80,28 @@ fn entity_impl(
 
         from_body.push(from_part);
 
+        let try_from_part = if field_params.default {
+            quote::quote! {
+                #name: match tuple.try_get(#column) {
+                    ::std::result::Result::Err(err @ #elephantry::Error::Unchanged(_)) => return ::std::result::Result::Err(err),
+                    value => value.unwrap_or_default(),
+                }
+            }
+        } else if is_option(ty) {
+            quote::quote! {
+                #name: match tuple.try_get(#column) {
+                    ::std::result::Result::Err(err @ #elephantry::Error::Unchanged(_)) => return ::std::result::Result::Err(err),
+                    value => value.ok(),
+                }
+            }
+        } else {
+            quote::quote! {
+                #name: tuple.try_get(#column)?
+            }
+        };
+
+        try_from_body.push(try_from_part);
+
         let get_part = if is_option(ty) {
             quote::quote! {
                 #column => match self.#name {
@@ -108,6 +132,13 @@ fn entity_impl(
                 }
             }
 
+            fn try_from(tuple: &#elephantry::Tuple<'_>) -> #elephantry::Result<Self>
+            {
+                ::std::result::Result::Ok(Self {
+                    #(#try_from_body, )*
+                })
+            }
+
             fn get(&self, field: &str) -> ::std::option::Option<&dyn #elephantry::ToSql> {
                 match field {
                     #(#get_body, )*
@@ -142,6 +173,7 @@ fn structure_impl(
 
     let mut primary_key = Vec::new();
     let mut columns = Vec::new();
+    let mut fields_body = Vec::new();
 
     for field in fields {
         let field_params = crate::params::Field::from_field(field)?;
@@ -154,6 +186,11 @@ fn structure_impl(
         }
 
         if field_params.r#virtual.is_none() {
+            let field = field.ident.as_ref().unwrap().unraw().to_string();
+            fields_body.push(quote::quote! {
+                #column => ::std::option::Option::Some(#field)
+            });
+
             columns.push(column);
         }
     }
@@ -168,6 +205,13 @@ fn structure_impl(
                     #(#primary_key, )*
                 ]
             }
+
+            fn field(column: &str) -> ::std::option::Option<&'static str> {
+                match column {
+                    #(#fields_body, )*
+                    _ => ::std::option::Option::None,
+                }
+            }
         }
 
         #[automatically_derived]